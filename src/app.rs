@@ -1,13 +1,31 @@
+use crate::backup::{read_backup, restore_backup};
+use crate::backup_list::BackupList;
+use crate::conflict_dialog::ConflictDialog;
+use crate::diff::unified_diff;
 use crate::editor::Editor;
+use crate::exclusive_set_input::ExclusiveSetInput;
+use crate::export_input::ExportInput;
+use crate::hosts::{check_hosts_decodable, read_sys_hosts, read_unmanaged_sys_lines};
 use crate::hosts_title_input::TitleInput;
-use crate::list::HostsList;
+use crate::list::{partially_written, write_edited_sys_hosts, HostsList};
+use crate::member_picker::MemberPicker;
 use crate::observer::Subject;
+use crate::password_cache::{PasswordCache, SecretPassword};
 use crate::password_input::PasswordInput;
 use crate::popup::Popup;
+use crate::preview::Preview;
+use crate::provenance::describe_changes;
+use crate::reconcile_dialog::{ReconcileAction, ReconcileDialog};
+use crate::remote::{is_refresh_due, spawn_fetch, FetchResult};
 use crate::search::Search;
 use crate::search_result::SearchResult;
+use crate::settings::read_settings;
+use crate::target::{get_targets, invalid_marker_message, invalid_markers, Target};
+use crate::text_format::normalize_line_endings;
 use crate::tip::Tip;
+use crate::url_input::UrlInput;
 use crate::util::Result;
+use chrono::{Local, Utc};
 use crossterm::event::KeyEventKind;
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers, MouseEventKind};
 use log::{debug, error};
@@ -17,6 +35,9 @@ use ratatui::{
     widgets::Clear,
     DefaultTerminal, Frame,
 };
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::time::{Duration, Instant};
 use std::{cell::RefCell, rc::Rc};
 
@@ -24,15 +45,47 @@ const MOUSE_SCROLL_THROTTLE_INTERVAL: u128 = 100;
 
 const POPUP_VISIBLE_INTERVAL: u128 = 600;
 
+const REMOTE_CHECK_INTERVAL: u128 = 1000;
+
 #[derive(Debug, Default, PartialEq)]
 enum Mode {
     Loading,
     #[default]
     Normal,
     EditingTitle,
+    EditingUrl,
+    PickingMembers,
+    PickingTargets,
+    PickingLines,
+    PickingExport,
+    EditingExportPath,
+    EditingExclusiveSet,
     EditingHosts,
+    EditingSystemHosts,
     InputPassword,
     Filter,
+    ConfirmConflict,
+    Reconcile,
+    Preview,
+    BackupList,
+}
+
+/// 需要写入系统 hosts 的操作，没有权限时输入密码后重试
+#[derive(Clone, Debug, PartialEq)]
+enum PendingWrite {
+    Toggle {
+        only_update_content: bool,
+    },
+    Restore(String),
+    /// 按当前的启用状态重新写入
+    Apply,
+    /// 用选中的行新建并启用 hosts，并从系统 hosts 中删除导入的行
+    AdoptLines {
+        title: String,
+        lines: Vec<(usize, String)>,
+    },
+    /// 写入在编辑器中修改后的系统 hosts
+    EditSystem(String),
 }
 
 pub struct App {
@@ -47,12 +100,48 @@ pub struct App {
     password_input: PasswordInput<'static>,
     instant: Instant,
     popup_instant: Instant,
-    cached_password: Option<String>,
+    password_cache: PasswordCache,
     search: Search,
     search_result: SearchResult,
     popup: Popup,
     show_popup: bool,
     popup_text: String,
+    conflict_dialog: ConflictDialog,
+    show_conflict_dialog: bool,
+    reconcile_dialog: ReconcileDialog,
+    show_reconcile_dialog: bool,
+    /// 启动时系统 hosts 中受管理区块的实际内容
+    reconcile_live: String,
+    conflict_return_mode: Mode,
+    pending_update_content: bool,
+    preview: Preview,
+    show_preview: bool,
+    preview_write: PendingWrite,
+    pending_write: PendingWrite,
+    backup_list: BackupList,
+    show_backup_list: bool,
+    url_input: UrlInput<'static>,
+    show_url_input: bool,
+    adding_remote: bool,
+    remote_title: String,
+    member_picker: MemberPicker,
+    show_member_picker: bool,
+    adding_group: bool,
+    group_title: String,
+    editing_group: Option<String>,
+    /// 系统 hosts 中受管理区块以外的行，选择后导入为新的 hosts
+    unmanaged_lines: Vec<(usize, String)>,
+    exclusive_set_input: ExclusiveSetInput<'static>,
+    show_exclusive_set_input: bool,
+    export_input: ExportInput<'static>,
+    show_export_input: bool,
+    /// 选中的要导出的 hosts，输入路径后导出
+    export_ids: Vec<String>,
+    remote_sender: Sender<(String, Result<FetchResult>)>,
+    remote_receiver: Receiver<(String, Result<FetchResult>)>,
+    remote_instant: Instant,
+    remote_attempts: HashMap<String, i64>,
+    refreshing: HashSet<String>,
 }
 
 fn title_input_area(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
@@ -62,6 +151,13 @@ fn title_input_area(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
     area
 }
 
+fn dialog_area(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
+    let vertical = Layout::vertical([Constraint::Percentage(percent_y)]).flex(Flex::Center);
+    let horizontal = Layout::horizontal([Constraint::Percentage(percent_x)]).flex(Flex::Center);
+    let [area] = horizontal.areas(vertical.areas::<1>(area)[0]);
+    area
+}
+
 fn popup_area(area: Rect, length: u16) -> Rect {
     let vertical = Layout::vertical([Constraint::Length(3)]).flex(Flex::Center);
     let horizontal = Layout::horizontal([Constraint::Length(length)]).flex(Flex::Center);
@@ -80,6 +176,7 @@ impl App {
         hosts_list_subject.borrow_mut().register(editor.clone());
         hosts_list.inject_subject(hosts_list_subject.clone());
         hosts_list.init();
+        let (remote_sender, remote_receiver) = mpsc::channel();
         App {
             running: false,
             hosts_list,
@@ -94,15 +191,55 @@ impl App {
             password_input: PasswordInput::new(),
             instant: Instant::now(),
             popup_instant: Instant::now(),
-            cached_password: None,
+            password_cache: PasswordCache::new(Duration::from_secs(
+                read_settings().password_cache_timeout,
+            )),
             popup,
             show_popup: false,
             popup_text: String::from(""),
+            conflict_dialog: ConflictDialog::new(),
+            show_conflict_dialog: false,
+            reconcile_dialog: ReconcileDialog::new(),
+            show_reconcile_dialog: false,
+            reconcile_live: String::new(),
+            conflict_return_mode: Mode::Normal,
+            pending_update_content: false,
+            preview: Preview::new(),
+            show_preview: false,
+            preview_write: PendingWrite::Toggle {
+                only_update_content: false,
+            },
+            pending_write: PendingWrite::Toggle {
+                only_update_content: false,
+            },
+            backup_list: BackupList::new(),
+            show_backup_list: false,
+            url_input: UrlInput::new(),
+            show_url_input: false,
+            adding_remote: false,
+            remote_title: String::new(),
+            member_picker: MemberPicker::new(),
+            show_member_picker: false,
+            adding_group: false,
+            group_title: String::new(),
+            editing_group: None,
+            unmanaged_lines: vec![],
+            exclusive_set_input: ExclusiveSetInput::new(),
+            show_exclusive_set_input: false,
+            export_input: ExportInput::new(),
+            show_export_input: false,
+            export_ids: vec![],
+            remote_sender,
+            remote_receiver,
+            remote_instant: Instant::now(),
+            remote_attempts: HashMap::new(),
+            refreshing: HashSet::new(),
         }
     }
 
     pub fn run(mut self, terminal: &mut DefaultTerminal) -> Result<()> {
         self.running = true;
+        self.check_managed_block();
         while self.running {
             if self.mode == Mode::Normal {
                 self.tip.show_line(0);
@@ -110,11 +247,15 @@ impl App {
                 self.tip.show_line(1);
             } else if self.mode == Mode::EditingHosts {
                 self.tip.show_line(2);
+            } else if self.mode == Mode::EditingSystemHosts {
+                self.tip.show_line(3);
             }
             if self.show_popup && self.popup_instant.elapsed().as_millis() > POPUP_VISIBLE_INTERVAL
             {
                 self.show_popup = false
             }
+            self.poll_remote();
+            self.password_cache.expire_idle();
             terminal.draw(|frame| {
                 self.draw(frame);
             })?;
@@ -129,7 +270,7 @@ impl App {
         let [filter_area, main_area, tip_area] = Layout::vertical([
             Constraint::Length(3),
             Constraint::Fill(1),
-            Constraint::Length(1),
+            Constraint::Length(self.tip.height(frame_area.width)),
         ])
         .areas(frame_area);
         let [left, right] =
@@ -147,6 +288,30 @@ impl App {
         if self.hosts_hosts_title_input {
             self.draw_title_input(frame_area, frame);
         }
+        if self.show_backup_list {
+            self.draw_backup_list(frame_area, frame);
+        }
+        if self.show_preview {
+            self.draw_preview(frame_area, frame);
+        }
+        if self.show_conflict_dialog {
+            self.draw_conflict_dialog(frame_area, frame);
+        }
+        if self.show_reconcile_dialog {
+            self.draw_reconcile_dialog(frame_area, frame);
+        }
+        if self.show_url_input {
+            self.draw_url_input(frame_area, frame);
+        }
+        if self.show_member_picker {
+            self.draw_member_picker(frame_area, frame);
+        }
+        if self.show_exclusive_set_input {
+            self.draw_exclusive_set_input(frame_area, frame);
+        }
+        if self.show_export_input {
+            self.draw_export_input(frame_area, frame);
+        }
         if self.show_password_input {
             self.draw_password_input(frame_area, frame);
         }
@@ -163,6 +328,34 @@ impl App {
         self.hosts_title_input.draw(area, buf);
     }
 
+    fn draw_url_input(&mut self, frame_area: Rect, frame: &mut Frame) {
+        let area = title_input_area(frame_area, 60, 20);
+        frame.render_widget(Clear, area);
+        let buf = frame.buffer_mut();
+        self.url_input.draw(area, buf);
+    }
+
+    fn draw_exclusive_set_input(&mut self, frame_area: Rect, frame: &mut Frame) {
+        let area = title_input_area(frame_area, 60, 20);
+        frame.render_widget(Clear, area);
+        let buf = frame.buffer_mut();
+        self.exclusive_set_input.draw(area, buf);
+    }
+
+    fn draw_export_input(&mut self, frame_area: Rect, frame: &mut Frame) {
+        let area = title_input_area(frame_area, 60, 20);
+        frame.render_widget(Clear, area);
+        let buf = frame.buffer_mut();
+        self.export_input.draw(area, buf);
+    }
+
+    fn draw_member_picker(&mut self, frame_area: Rect, frame: &mut Frame) {
+        let area = dialog_area(frame_area, 50, 60);
+        frame.render_widget(Clear, area);
+        let buf = frame.buffer_mut();
+        self.member_picker.draw(area, buf);
+    }
+
     fn draw_password_input(&mut self, frame_area: Rect, frame: &mut Frame) {
         let buf = frame.buffer_mut();
         let area = title_input_area(frame_area, 60, 20);
@@ -171,6 +364,34 @@ impl App {
         self.password_input.draw(area, buf);
     }
 
+    fn draw_conflict_dialog(&mut self, frame_area: Rect, frame: &mut Frame) {
+        let area = dialog_area(frame_area, 70, 60);
+        frame.render_widget(Clear, area);
+        let buf = frame.buffer_mut();
+        self.conflict_dialog.draw(area, buf);
+    }
+
+    fn draw_reconcile_dialog(&mut self, frame_area: Rect, frame: &mut Frame) {
+        let area = dialog_area(frame_area, 80, 70);
+        frame.render_widget(Clear, area);
+        let buf = frame.buffer_mut();
+        self.reconcile_dialog.draw(area, buf);
+    }
+
+    fn draw_preview(&mut self, frame_area: Rect, frame: &mut Frame) {
+        let area = dialog_area(frame_area, 80, 80);
+        frame.render_widget(Clear, area);
+        let buf = frame.buffer_mut();
+        self.preview.draw(area, buf);
+    }
+
+    fn draw_backup_list(&mut self, frame_area: Rect, frame: &mut Frame) {
+        let area = dialog_area(frame_area, 70, 70);
+        frame.render_widget(Clear, area);
+        let buf = frame.buffer_mut();
+        self.backup_list.draw(area, buf);
+    }
+
     fn draw_popup(&mut self, frame_area: Rect, frame: &mut Frame) {
         let buf = frame.buffer_mut();
         let area = popup_area(frame_area, self.popup_text.len() as u16 + 4);
@@ -195,13 +416,15 @@ impl App {
                     if e.kind == MouseEventKind::ScrollUp {
                         if self.mode == Mode::Normal {
                             self.hosts_list.toggle_previous();
-                        } else if self.mode == Mode::EditingHosts {
+                        } else if matches!(self.mode, Mode::EditingHosts | Mode::EditingSystemHosts)
+                        {
                             self.editor.borrow_mut().cursor_move_up();
                         }
                     } else if e.kind == MouseEventKind::ScrollDown {
                         if self.mode == Mode::Normal {
                             self.hosts_list.toggle_next();
-                        } else if self.mode == Mode::EditingHosts {
+                        } else if matches!(self.mode, Mode::EditingHosts | Mode::EditingSystemHosts)
+                        {
                             self.editor.borrow_mut().cursor_move_down();
                         }
                     }
@@ -220,20 +443,413 @@ impl App {
             }
             Err(e) => {
                 if e.to_string() == String::from("no permission") {
-                    if !self.show_popup {
-                        self.show_popup = true;
-                        self.popup_instant = Instant::now();
-                        self.popup_text = String::from("没有写入 Hosts 文件的权限");
-                    }
-                    if cfg!(target_os = "windows") {
+                    let writer = read_settings().privileged_writer();
+                    if cfg!(target_os = "windows") || !writer.needs_password() {
+                        self.open_popup(format!(
+                            "没有写入 Hosts 文件的权限，{}",
+                            writer.permission_hint()
+                        ));
                         return;
                     }
+                    self.open_popup("没有写入 Hosts 文件的权限");
                     self.mode = Mode::InputPassword;
                     self.show_password_input = true;
+                    return;
+                }
+                // 其他错误重新输入密码也不会成功，关闭密码框并显示真正的原因
+                self.mode = Mode::Normal;
+                self.show_password_input = false;
+                if e.to_string() == "unreadable hosts" {
+                    self.open_popup("无法读取 Hosts 文件，已停止写入");
+                } else if e.to_string() == "essential entries missing" {
+                    self.open_popup("写入后会缺少 localhost 条目，已停止写入");
+                } else if e.to_string() == "undecodable hosts" {
+                    self.open_popup("系统 hosts 含有无法按原编码解码的内容，已停止写入");
+                } else if e.to_string() == "managed block changed" {
+                    self.open_popup("受管理区块只能通过启用、停用 hosts 修改，已停止写入");
+                } else if let Some(targets) = partially_written(&e) {
+                    self.open_popup(format!("写入中途失败，{} 已写入且无法恢复", targets));
+                } else if e.to_string() == "invalid marker" {
+                    self.open_popup(invalid_marker_message());
+                } else if e.to_string() == "hosts changed" {
+                    self.open_popup("系统 hosts 已被修改，请重新选择要导入的行");
+                } else if e.to_string() == "invalid hosts" {
+                    self.open_popup("hosts 内容校验不通过，已阻止启用");
                 } else {
                     error!("{e}");
+                    self.open_popup(format!("写入失败：{e}"));
+                }
+            }
+        }
+    }
+
+    /// 写入前先检测冲突，有冲突时弹出确认框
+    fn toggle_selected(&mut self, only_update_content: bool) {
+        match self
+            .hosts_list
+            .check_selected_conflicts(only_update_content)
+        {
+            Ok(conflicts) if !conflicts.is_empty() => {
+                self.conflict_dialog.set_conflicts(&conflicts);
+                self.pending_update_content = only_update_content;
+                self.conflict_return_mode = std::mem::take(&mut self.mode);
+                self.mode = Mode::ConfirmConflict;
+                self.show_conflict_dialog = true;
+            }
+            res => {
+                if let Err(e) = res {
+                    error!("{e}");
+                }
+                self.apply_toggle(only_update_content);
+            }
+        }
+    }
+
+    fn apply_toggle(&mut self, only_update_content: bool) {
+        let mode = std::mem::take(&mut self.mode);
+        self.run_write(PendingWrite::Toggle {
+            only_update_content,
+        });
+        if only_update_content && !self.show_password_input {
+            self.mode = mode;
+        }
+    }
+
+    fn run_write(&mut self, write: PendingWrite) {
+        let password = self.password_cache.get().cloned();
+        self.run_write_with_password(write, password.as_ref());
+    }
+
+    fn run_write_with_password(&mut self, write: PendingWrite, password: Option<&SecretPassword>) {
+        let password = password.map(SecretPassword::expose);
+        let res = match &write {
+            PendingWrite::Toggle {
+                only_update_content,
+            } => self
+                .hosts_list
+                .toggle_on_off(password, *only_update_content),
+            PendingWrite::Apply => self.hosts_list.apply_enabled(password),
+            PendingWrite::AdoptLines { title, lines } => {
+                let res = self.hosts_list.adopt_lines(title.clone(), lines, password);
+                if res.is_ok() {
+                    self.open_popup(format!("已导入为 {}", title));
+                    self.hosts_list.dispatch_subject();
+                }
+                res.map(|_| ())
+            }
+            PendingWrite::EditSystem(text) => {
+                let res = write_edited_sys_hosts(text, password);
+                if res.is_ok() {
+                    self.open_popup("已写入系统 hosts，原文件已备份");
+                }
+                self.hosts_list.dispatch_subject();
+                res
+            }
+            PendingWrite::Restore(name) => {
+                let res = restore_backup(name, password);
+                if res.is_ok() {
+                    self.show_backup_list = false;
+                    self.hosts_list.dispatch_subject();
+                }
+                res
+            }
+        };
+        if res.is_ok() {
+            if let PendingWrite::Restore(_) = write {
+                self.open_popup("已恢复备份");
+            }
+        }
+        self.pending_write = write;
+        self.update_show_password_input(res);
+    }
+
+    fn open_preview(&mut self, title: &str, diff: &str, confirm_label: &str, write: PendingWrite) {
+        self.preview.set_diff(title, diff, confirm_label);
+        self.preview_write = write;
+        self.show_preview = true;
+        self.mode = Mode::Preview;
+    }
+
+    /// 退出系统 hosts 的编辑模式，内容有修改时显示差异，确认后再写入
+    fn finish_system_edit(&mut self) {
+        let text = {
+            let mut editor = self.editor.borrow_mut();
+            editor.end_system_edit();
+            editor.inactivate();
+            editor.get_text()
+        };
+        self.mode = Mode::Normal;
+        match read_sys_hosts() {
+            Ok(current) => {
+                let current = normalize_line_endings(&current);
+                if current == text {
+                    return;
+                }
+                let diff = unified_diff(&current, &text, "当前系统 hosts", "修改后");
+                self.open_preview(
+                    "修改系统 hosts",
+                    &diff,
+                    "写入",
+                    PendingWrite::EditSystem(text),
+                );
+            }
+            Err(e) => {
+                error!("{e}");
+                self.hosts_list.dispatch_subject();
+            }
+        }
+    }
+
+    fn refresh_remote(&mut self, id: String) {
+        if self.refreshing.contains(&id) {
+            return;
+        }
+        let remote = self
+            .hosts_list
+            .get_remote_items()
+            .into_iter()
+            .find(|item| *item.id() == id)
+            .and_then(|item| item.remote().cloned());
+        if let Some(remote) = remote {
+            self.remote_attempts
+                .insert(id.clone(), Utc::now().timestamp());
+            self.refreshing.insert(id.clone());
+            spawn_fetch(id, &remote, self.remote_sender.clone());
+        }
+    }
+
+    /// 处理后台拉取的结果，并按间隔触发需要自动刷新的远程 hosts
+    fn poll_remote(&mut self) {
+        while let Ok((id, res)) = self.remote_receiver.try_recv() {
+            self.refreshing.remove(&id);
+            let password = self.password_cache.get().cloned();
+            let res = self.hosts_list.apply_remote_result(
+                &id,
+                res,
+                password.as_ref().map(SecretPassword::expose),
+            );
+            match res {
+                Err(e) if e.to_string() == "no permission" => {
+                    self.open_popup("远程 hosts 已更新，但没有写入 Hosts 文件的权限");
+                }
+                Err(e) if e.to_string() != "fetch failed" => {
+                    error!("{e}");
+                    self.open_popup(format!("更新远程 hosts 失败：{e}"));
+                }
+                Err(e) => error!("{e}"),
+                Ok(_) => {}
+            }
+        }
+        if self.remote_instant.elapsed().as_millis() < REMOTE_CHECK_INTERVAL {
+            return;
+        }
+        self.remote_instant = Instant::now();
+        let now = Utc::now().timestamp();
+        let due = self
+            .hosts_list
+            .get_remote_items()
+            .into_iter()
+            .filter(|item| {
+                is_refresh_due(
+                    item.remote().unwrap(),
+                    now,
+                    self.remote_attempts.get(item.id()).copied(),
+                )
+            })
+            .map(|item| item.id().to_owned())
+            .collect::<Vec<_>>();
+        for id in due {
+            self.refresh_remote(id);
+        }
+    }
+
+    /// `group` 为 None 时新建分组
+    fn open_member_picker(&mut self, group: Option<String>) {
+        let members = group
+            .as_ref()
+            .and_then(|id| {
+                self.hosts_list
+                    .get_all_hosts_item_list()
+                    .iter()
+                    .find(|item| item.id() == id)
+            })
+            .map(|item| item.members().clone())
+            .unwrap_or_default();
+        self.member_picker
+            .set_candidates(&self.hosts_list.get_member_candidates(), &members);
+        self.editing_group = group;
+        self.show_member_picker = true;
+        self.mode = Mode::PickingMembers;
+    }
+
+    fn confirm_members(&mut self) {
+        let members = self.member_picker.get_members();
+        let res = match self.editing_group.take() {
+            Some(id) => self
+                .hosts_list
+                .update_group_members(&id, members)
+                .map(|_| self.hosts_list.is_applied(&id)),
+            None => {
+                let title = std::mem::take(&mut self.group_title);
+                self.hosts_list
+                    .add_group_item(title, members)
+                    .map(|_| false)
+            }
+        };
+        match res {
+            Ok(true) => self.run_write(PendingWrite::Apply),
+            Ok(false) => {}
+            Err(e) => error!("{e}"),
+        }
+    }
+
+    /// 列出系统 hosts 中受管理区块以外的行，选择要导入到新的 hosts 中的行
+    fn open_line_picker(&mut self) {
+        match read_unmanaged_sys_lines() {
+            Ok(lines) if lines.is_empty() => self.open_popup("受管理区块以外没有可以导入的行"),
+            Ok(lines) => {
+                let options = lines
+                    .iter()
+                    .map(|(index, line)| (index.to_string(), format!("{:>4}  {}", index + 1, line)))
+                    .collect::<Vec<_>>();
+                self.member_picker
+                    .set_options("导入到新的 hosts", &options, &[]);
+                self.unmanaged_lines = lines;
+                self.show_member_picker = true;
+                self.mode = Mode::PickingLines;
+            }
+            Err(e) => error!("{e}"),
+        }
+    }
+
+    /// 用选中的行新建 hosts，再启用它并从系统 hosts 中删除这些行
+    fn confirm_lines(&mut self) {
+        let picked = self.member_picker.get_members();
+        let lines = std::mem::take(&mut self.unmanaged_lines)
+            .into_iter()
+            .filter(|(index, _)| picked.contains(&index.to_string()))
+            .collect::<Vec<_>>();
+        if lines.is_empty() {
+            return;
+        }
+        let title = format!("导入的 hosts {}", Local::now().format("%m-%d %H:%M"));
+        self.run_write(PendingWrite::AdoptLines { title, lines });
+    }
+
+    /// 选择要导出的 hosts，默认选中已启用的
+    fn open_export_picker(&mut self) {
+        let options = self
+            .hosts_list
+            .get_all_hosts_item_list()
+            .iter()
+            .filter(|item| item.id() != "system")
+            .map(|item| (item.id().to_owned(), item.title().to_owned()))
+            .collect::<Vec<_>>();
+        let enabled = self
+            .hosts_list
+            .enabled_items(&String::new(), false)
+            .iter()
+            .map(|item| item.id().to_owned())
+            .collect::<Vec<_>>();
+        self.member_picker
+            .set_options("导出的 hosts", &options, &enabled);
+        self.show_member_picker = true;
+        self.mode = Mode::PickingExport;
+    }
+
+    fn confirm_export(&mut self) {
+        self.export_ids = self.member_picker.get_members();
+        if self.export_ids.is_empty() {
+            self.open_popup("没有选择要导出的 hosts");
+            return;
+        }
+        self.show_export_input = true;
+        self.mode = Mode::EditingExportPath;
+    }
+
+    /// 把组合后的 hosts 导出到文件，不写入系统 hosts
+    fn export_to_file(&mut self, path: String) {
+        let ids = std::mem::take(&mut self.export_ids);
+        let res = self
+            .hosts_list
+            .export_content(Some(&ids), self.export_input.is_full(), false)
+            .and_then(|content| Ok(fs::write(&path, content)?));
+        match res {
+            Ok(_) => self.open_popup(format!("已导出到 {}", path)),
+            Err(e) if e.to_string() == "unreadable hosts" => {
+                self.open_popup("无法读取 Hosts 文件，无法生成完整内容")
+            }
+            Err(e) => {
+                error!("{e}");
+                self.open_popup(format!("无法写入 {}", path));
+            }
+        }
+    }
+
+    /// 已启用的 hosts 修改目标后重新写入，从目标中移除的文件也会被清理
+    fn confirm_targets(&mut self) {
+        let applied = match self.hosts_list.get_selected_id().clone() {
+            Some(id) => self.hosts_list.is_applied(&id),
+            None => return,
+        };
+        let targets = self.member_picker.get_members();
+        match self.hosts_list.set_targets(targets) {
+            Ok(_) if applied => self.run_write(PendingWrite::Apply),
+            Ok(_) => {}
+            Err(e) => error!("{e}"),
+        }
+    }
+
+    /// 启动时系统 hosts 的受管理区块与配置不一致（如被手动修改或恢复）时弹出处理对话框
+    fn check_managed_block(&mut self) {
+        if self.hosts_list.load_error().is_some() {
+            return;
+        }
+        if !invalid_markers().is_empty() {
+            self.open_popup(invalid_marker_message());
+            return;
+        }
+        match self.hosts_list.check_managed_block() {
+            Ok(Some((expected, live))) => {
+                let changes = describe_changes(&expected, &live);
+                let diff = unified_diff(&expected, &live, "按配置生成", "系统 hosts");
+                self.reconcile_dialog.set_diff(&changes, &diff);
+                self.reconcile_live = live;
+                self.show_reconcile_dialog = true;
+                self.mode = Mode::Reconcile;
+            }
+            Ok(None) => {}
+            Err(e) => error!("{e}"),
+        }
+    }
+
+    fn reconcile(&mut self, action: ReconcileAction) {
+        let live = std::mem::take(&mut self.reconcile_live);
+        match action {
+            ReconcileAction::Reapply => self.run_write(PendingWrite::Apply),
+            ReconcileAction::Adopt => {
+                let title = format!("导入的 hosts {}", Local::now().format("%m-%d %H:%M"));
+                match self.hosts_list.adopt_managed_block(title.clone(), live) {
+                    Ok(_) => {
+                        self.open_popup(format!("已导入为 {}", title));
+                        self.run_write(PendingWrite::Apply);
+                    }
+                    Err(e) => error!("{e}"),
                 }
             }
+            ReconcileAction::DisableAll => match self.hosts_list.disable_all() {
+                Ok(_) => self.run_write(PendingWrite::Apply),
+                Err(e) => error!("{e}"),
+            },
+            ReconcileAction::Ignore => {}
+        }
+    }
+
+    fn open_popup(&mut self, text: impl Into<String>) {
+        if !self.show_popup {
+            self.show_popup = true;
+            self.popup_instant = Instant::now();
+            self.popup_text = text.into();
         }
     }
 
@@ -245,6 +861,91 @@ impl App {
                     self.mode = Mode::EditingTitle;
                 }
             }
+            (KeyModifiers::SHIFT, KeyCode::Char('a') | KeyCode::Char('A'))
+                if self.mode == Mode::Normal =>
+            {
+                self.adding_remote = true;
+                self.hosts_hosts_title_input = true;
+                self.mode = Mode::EditingTitle;
+            }
+            (KeyModifiers::SHIFT, KeyCode::Char('g') | KeyCode::Char('G'))
+                if self.mode == Mode::Normal =>
+            {
+                self.adding_group = true;
+                self.hosts_hosts_title_input = true;
+                self.mode = Mode::EditingTitle;
+            }
+            (KeyModifiers::NONE, KeyCode::Char('g')) => {
+                if let Some(item) = self.hosts_list.get_selected_item() {
+                    if item.is_group() {
+                        self.open_member_picker(Some(item.id().to_owned()));
+                    }
+                }
+            }
+            (KeyModifiers::NONE, KeyCode::Char('t')) => {
+                if let Some(item) = self.hosts_list.get_selected_item() {
+                    if item.id() != "system" {
+                        let options = get_targets()
+                            .into_iter()
+                            .map(|target| {
+                                let label = format!("{} ({})", target.name, target.path);
+                                (target.name, label)
+                            })
+                            .collect::<Vec<_>>();
+                        self.member_picker
+                            .set_options("写入目标", &options, &item.targets());
+                        self.show_member_picker = true;
+                        self.mode = Mode::PickingTargets;
+                    }
+                }
+            }
+            (KeyModifiers::NONE, KeyCode::Char('i') | KeyCode::Char('I'))
+                if self.hosts_list.get_selected_id().as_deref() == Some("system") =>
+            {
+                self.open_line_picker();
+            }
+            (KeyModifiers::SHIFT, KeyCode::Char('e') | KeyCode::Char('E'))
+                if self.hosts_list.get_selected_id().as_deref() == Some("system") =>
+            {
+                match check_hosts_decodable(&Target::system()) {
+                    Err(e) if e.to_string() == "undecodable hosts" => {
+                        self.open_popup("系统 hosts 含有无法按原编码解码的内容，不能在这里编辑");
+                        return Ok(());
+                    }
+                    Err(e) => {
+                        error!("{e}");
+                        return Ok(());
+                    }
+                    Ok(_) => {}
+                }
+                let res = self.editor.borrow_mut().begin_system_edit();
+                if let Err(e) = res {
+                    self.open_popup(invalid_marker_message());
+                    error!("{e}");
+                    return Ok(());
+                }
+                // 重新读取系统 hosts，在最新的内容上修改
+                self.hosts_list.dispatch_subject();
+                self.mode = Mode::EditingSystemHosts;
+            }
+            (KeyModifiers::NONE, KeyCode::Char('o') | KeyCode::Char('O')) => {
+                self.open_export_picker();
+            }
+            (KeyModifiers::NONE, KeyCode::Char('x') | KeyCode::Char('X')) => {
+                if let Some(item) = self.hosts_list.get_selected_item() {
+                    if item.id() != "system" {
+                        let exclusive_set = item.exclusive_set().cloned().unwrap_or_default();
+                        self.exclusive_set_input.set_text(exclusive_set);
+                        self.show_exclusive_set_input = true;
+                        self.mode = Mode::EditingExclusiveSet;
+                    }
+                }
+            }
+            (KeyModifiers::NONE, KeyCode::Char('u') | KeyCode::Char('U')) => {
+                if let Some(id) = self.hosts_list.get_selected_id().clone() {
+                    self.refresh_remote(id);
+                }
+            }
             (KeyModifiers::SHIFT, KeyCode::Char('d') | KeyCode::Char('D')) => {
                 self.hosts_list.delete_current_item();
             }
@@ -271,6 +972,32 @@ impl App {
                     }
                 }
             }
+            (KeyModifiers::NONE, KeyCode::Char('p') | KeyCode::Char('P')) => {
+                match self.hosts_list.preview_toggle() {
+                    Ok((current, toggled)) => {
+                        let diff = unified_diff(&current, &toggled, "当前 hosts", "切换后 hosts");
+                        self.open_preview(
+                            "预览切换结果",
+                            &diff,
+                            "应用",
+                            PendingWrite::Toggle {
+                                only_update_content: false,
+                            },
+                        );
+                    }
+                    Err(e) => error!("{e}"),
+                }
+            }
+            (KeyModifiers::SHIFT, KeyCode::Char('l') | KeyCode::Char('L'))
+                if self.password_cache.forget() =>
+            {
+                self.open_popup("已清除缓存的密码");
+            }
+            (KeyModifiers::SHIFT, KeyCode::Char('r') | KeyCode::Char('R')) => {
+                self.backup_list.reload();
+                self.show_backup_list = true;
+                self.mode = Mode::BackupList;
+            }
             (_, KeyCode::Char('f') | KeyCode::Char('F')) => {
                 self.mode = Mode::Filter;
             }
@@ -281,15 +1008,13 @@ impl App {
                 self.hosts_list.toggle_next();
             }
             (_, KeyCode::Enter) => {
-                let res = self
-                    .hosts_list
-                    .toggle_on_off(self.cached_password.clone(), false);
-                self.update_show_password_input(res);
+                self.toggle_selected(false);
             }
             (_, KeyCode::Right) => {
-                if let Some(id) = self.hosts_list.get_selected_id() {
+                if let Some(item) = self.hosts_list.get_selected_item() {
                     self.mode = Mode::EditingHosts;
-                    self.editor.borrow_mut().set_id(id.to_owned());
+                    self.editor.borrow_mut().set_id(item.id().to_owned());
+                    self.editor.borrow_mut().set_read_only(item.is_read_only());
                     self.editor.borrow_mut().activate();
                 }
             }
@@ -311,11 +1036,21 @@ impl App {
                     (true, None, _) => {
                         self.mode = Mode::Normal;
                         self.hosts_hosts_title_input = false;
+                        self.adding_remote = false;
+                        self.adding_group = false;
                     }
                     (true, Some(title), is_new) => {
                         self.mode = Mode::Normal;
                         self.hosts_hosts_title_input = false;
-                        if is_new {
+                        if self.adding_remote {
+                            self.remote_title = title;
+                            self.show_url_input = true;
+                            self.mode = Mode::EditingUrl;
+                        } else if self.adding_group {
+                            self.adding_group = false;
+                            self.group_title = title;
+                            self.mode = Mode::PickingMembers;
+                        } else if is_new {
                             self.hosts_list.add_item(title, "".to_owned());
                         } else {
                             self.hosts_list.update_item_title(title);
@@ -323,8 +1058,104 @@ impl App {
                     }
                     _ => {}
                 });
+                if self.mode == Mode::PickingMembers {
+                    self.open_member_picker(None);
+                }
                 return Ok(());
             }
+            Mode::EditingUrl => {
+                match self.url_input.handle_event(event) {
+                    (true, None) => {
+                        self.show_url_input = false;
+                        self.adding_remote = false;
+                        self.mode = Mode::Normal;
+                    }
+                    (true, Some(url)) => {
+                        self.show_url_input = false;
+                        self.adding_remote = false;
+                        self.mode = Mode::Normal;
+                        let interval = read_settings().remote_refresh_interval;
+                        let title = std::mem::take(&mut self.remote_title);
+                        match self.hosts_list.add_remote_item(title, url, interval) {
+                            Ok(id) => self.refresh_remote(id),
+                            Err(e) => error!("{e}"),
+                        }
+                    }
+                    _ => {}
+                }
+                Ok(())
+            }
+            Mode::EditingExclusiveSet => {
+                if let (true, exclusive_set) = self.exclusive_set_input.handle_event(event) {
+                    self.show_exclusive_set_input = false;
+                    self.mode = Mode::Normal;
+                    if let Some(exclusive_set) = exclusive_set {
+                        match self.hosts_list.set_exclusive_set(Some(exclusive_set)) {
+                            Err(e) if e.to_string() == "exclusive set conflict" => {
+                                self.open_popup("该互斥集合中已有启用的 hosts");
+                            }
+                            Err(e) => error!("{e}"),
+                            Ok(_) => {}
+                        }
+                    }
+                }
+                Ok(())
+            }
+            Mode::PickingMembers => {
+                if let Some(confirm) = self.member_picker.handle_event(event) {
+                    self.show_member_picker = false;
+                    self.mode = Mode::Normal;
+                    if confirm {
+                        self.confirm_members();
+                    } else {
+                        self.editing_group = None;
+                    }
+                }
+                Ok(())
+            }
+            Mode::PickingLines => {
+                if let Some(confirm) = self.member_picker.handle_event(event) {
+                    self.show_member_picker = false;
+                    self.mode = Mode::Normal;
+                    if confirm {
+                        self.confirm_lines();
+                    } else {
+                        self.unmanaged_lines.clear();
+                    }
+                }
+                Ok(())
+            }
+            Mode::PickingExport => {
+                if let Some(confirm) = self.member_picker.handle_event(event) {
+                    self.show_member_picker = false;
+                    self.mode = Mode::Normal;
+                    if confirm {
+                        self.confirm_export();
+                    }
+                }
+                Ok(())
+            }
+            Mode::EditingExportPath => {
+                if let (true, path) = self.export_input.handle_event(event) {
+                    self.show_export_input = false;
+                    self.mode = Mode::Normal;
+                    match path {
+                        Some(path) => self.export_to_file(path),
+                        None => self.export_ids.clear(),
+                    }
+                }
+                Ok(())
+            }
+            Mode::PickingTargets => {
+                if let Some(confirm) = self.member_picker.handle_event(event) {
+                    self.show_member_picker = false;
+                    self.mode = Mode::Normal;
+                    if confirm {
+                        self.confirm_targets();
+                    }
+                }
+                Ok(())
+            }
             Mode::EditingHosts => {
                 let res = self.editor.borrow_mut().handle_event(event);
                 match res {
@@ -334,11 +1165,8 @@ impl App {
                             self.mode = Mode::Normal;
                             old_mode = Mode::Normal;
                         }
-                        let toggled_res = self
-                            .hosts_list
-                            .toggle_on_off(self.cached_password.clone(), true);
-                        self.update_show_password_input(toggled_res);
                         self.mode = old_mode;
+                        self.toggle_selected(true);
                         return Ok(());
                     }
                     _ => {
@@ -346,6 +1174,12 @@ impl App {
                     }
                 };
             }
+            Mode::EditingSystemHosts => {
+                if self.editor.borrow_mut().handle_event(event).is_some() {
+                    self.finish_system_edit();
+                }
+                Ok(())
+            }
             Mode::InputPassword => {
                 let res = self.password_input.handle_event(event);
                 match res {
@@ -353,15 +1187,94 @@ impl App {
                         self.mode = Mode::Normal;
                         self.show_password_input = false;
                     }
-                    (true, password) => {
-                        self.cached_password = password.clone();
-                        let res = self.hosts_list.toggle_on_off(password, false);
-                        self.update_show_password_input(res);
+                    (true, Some(password)) => {
+                        self.run_write_with_password(self.pending_write.clone(), Some(&password));
+                        self.password_cache.set(password);
                     }
                     _ => {}
                 };
                 return Ok(());
             }
+            Mode::Reconcile => {
+                if let Some(action) = self.reconcile_dialog.handle_event(event) {
+                    self.show_reconcile_dialog = false;
+                    self.mode = Mode::Normal;
+                    self.reconcile(action);
+                }
+                Ok(())
+            }
+            Mode::ConfirmConflict => {
+                if let Some(proceed) = self.conflict_dialog.handle_event(event) {
+                    self.show_conflict_dialog = false;
+                    self.mode = std::mem::take(&mut self.conflict_return_mode);
+                    if proceed {
+                        self.apply_toggle(self.pending_update_content);
+                    }
+                }
+                Ok(())
+            }
+            Mode::Preview => {
+                if let Some(apply) = self.preview.handle_event(event) {
+                    self.show_preview = false;
+                    match self.preview_write.clone() {
+                        PendingWrite::Restore(name) => {
+                            self.mode = Mode::BackupList;
+                            if apply {
+                                self.run_write(PendingWrite::Restore(name));
+                            }
+                        }
+                        PendingWrite::Toggle { .. } => {
+                            self.mode = Mode::Normal;
+                            if apply {
+                                self.toggle_selected(false);
+                            }
+                        }
+                        write @ (PendingWrite::Apply | PendingWrite::AdoptLines { .. }) => {
+                            self.mode = Mode::Normal;
+                            if apply {
+                                self.run_write(write);
+                            }
+                        }
+                        write @ PendingWrite::EditSystem(_) => {
+                            self.mode = Mode::Normal;
+                            if apply {
+                                self.run_write(write);
+                            } else {
+                                // 放弃修改，编辑器重新显示系统 hosts 的内容
+                                self.hosts_list.dispatch_subject();
+                            }
+                        }
+                    }
+                }
+                Ok(())
+            }
+            Mode::BackupList => {
+                match self.backup_list.handle_event(event) {
+                    Some(true) => {
+                        let name = self.backup_list.get_selected().unwrap().name.clone();
+                        match read_sys_hosts()
+                            .and_then(|current| read_backup(&name).map(|backup| (current, backup)))
+                        {
+                            Ok((current, backup)) => {
+                                let diff = unified_diff(&current, &backup, "当前 hosts", &name);
+                                self.open_preview(
+                                    "恢复备份",
+                                    &diff,
+                                    "恢复",
+                                    PendingWrite::Restore(name),
+                                );
+                            }
+                            Err(e) => error!("{e}"),
+                        }
+                    }
+                    Some(false) => {
+                        self.show_backup_list = false;
+                        self.mode = Mode::Normal;
+                    }
+                    None => {}
+                }
+                Ok(())
+            }
             Mode::Filter => {
                 if event.code == KeyCode::Esc {
                     self.mode = Mode::Normal;
@@ -379,9 +1292,16 @@ impl App {
                         self.mode = Mode::EditingHosts;
                         self.search.clear();
                         self.hosts_list.set_selected_item(item_id.to_owned());
+                        let read_only = self
+                            .hosts_list
+                            .get_selected_item()
+                            .is_some_and(|item| item.is_read_only());
                         self.editor.borrow_mut().set_id(item_id.to_owned());
+                        self.editor.borrow_mut().set_read_only(read_only);
                         self.editor.borrow_mut().activate();
-                        self.editor.borrow_mut().jump_curosr(row.to_owned().saturating_sub(1));
+                        self.editor
+                            .borrow_mut()
+                            .jump_curosr(row.to_owned().saturating_sub(1));
                     });
                 }
                 return Ok(());
@@ -0,0 +1,253 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use log::error;
+use uuid::Uuid;
+
+use crate::util::Result;
+
+/// 原子写入的各个步骤，测试中用来模拟在某一步失败
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WriteStep {
+    CreateTemp,
+    Write,
+    Sync,
+    CopyMetadata,
+    Rename,
+}
+
+/// 先写入同目录下的临时文件并 fsync，再 rename 覆盖目标文件，中途失败时目标文件保持不变。
+/// 临时文件会继承目标文件的属主、权限和扩展属性，无法继承属主或权限时报错，扩展属性复制失败
+/// 只记录日志。只有目标不能被替换（如容器中挂载的 /etc/hosts，或者跨设备）时才退回到直接覆盖写入
+pub fn write_atomic(path: impl AsRef<Path>, content: impl AsRef<[u8]>) -> Result<()> {
+    write_atomic_with(path.as_ref(), content.as_ref(), &|_| Ok(()))
+}
+
+fn write_atomic_with(
+    path: &Path,
+    content: &[u8],
+    inject: &dyn Fn(WriteStep) -> io::Result<()>,
+) -> Result<()> {
+    let temp_path = get_temp_path(path);
+    match replace_with_temp(path, &temp_path, content, inject) {
+        Ok(_) => Ok(()),
+        Err((step, e)) => {
+            if step != WriteStep::CreateTemp {
+                let _ = fs::remove_file(&temp_path);
+            }
+            if should_write_in_place(step, &e) {
+                error!("atomic write {:?} failed at {:?}: {e}", path, step);
+                write_in_place(path, content, inject)?;
+                Ok(())
+            } else {
+                Err(e.into())
+            }
+        }
+    }
+}
+
+fn get_temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.{}.tmp", name, Uuid::new_v4()))
+}
+
+fn replace_with_temp(
+    path: &Path,
+    temp_path: &Path,
+    content: &[u8],
+    inject: &dyn Fn(WriteStep) -> io::Result<()>,
+) -> std::result::Result<(), (WriteStep, io::Error)> {
+    let step = |step: WriteStep, res: io::Result<()>| res.map_err(|e| (step, e));
+    let mut file = step(WriteStep::CreateTemp, inject(WriteStep::CreateTemp)).and_then(|_| {
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(temp_path)
+            .map_err(|e| (WriteStep::CreateTemp, e))
+    })?;
+    step(
+        WriteStep::Write,
+        inject(WriteStep::Write).and_then(|_| file.write_all(content)),
+    )?;
+    step(
+        WriteStep::Sync,
+        inject(WriteStep::Sync).and_then(|_| file.sync_all()),
+    )?;
+    drop(file);
+    step(
+        WriteStep::CopyMetadata,
+        inject(WriteStep::CopyMetadata).and_then(|_| copy_metadata(path, temp_path)),
+    )?;
+    step(
+        WriteStep::Rename,
+        inject(WriteStep::Rename).and_then(|_| fs::rename(temp_path, path)),
+    )?;
+    if let Err(e) = sync_parent_dir(path) {
+        error!("{e}");
+    }
+    Ok(())
+}
+
+/// 只有 rename 因为 EBUSY、EXDEV 失败时才覆盖写入，其他失败（如磁盘已满、没有权限、
+/// 无法继承属主）直接报错，避免写出属主或权限不同的文件
+fn should_write_in_place(step: WriteStep, e: &io::Error) -> bool {
+    step == WriteStep::Rename
+        && matches!(e.kind(), ErrorKind::ResourceBusy | ErrorKind::CrossesDevices)
+}
+
+fn write_in_place(
+    path: &Path,
+    content: &[u8],
+    inject: &dyn Fn(WriteStep) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)?;
+    inject(WriteStep::Write)?;
+    file.write_all(content)?;
+    inject(WriteStep::Sync)?;
+    file.sync_all()
+}
+
+#[cfg(unix)]
+fn copy_metadata(from: &Path, to: &Path) -> io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = match fs::metadata(from) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    let temp = fs::metadata(to)?;
+    if temp.uid() != metadata.uid() || temp.gid() != metadata.gid() {
+        std::os::unix::fs::chown(to, Some(metadata.uid()), Some(metadata.gid()))?;
+    }
+    fs::set_permissions(to, metadata.permissions())?;
+    if let Err(e) = copy_xattrs(from, to) {
+        error!("copy xattrs of {:?} failed: {e}", from);
+    }
+    Ok(())
+}
+
+/// 扩展属性（如 SELinux 标签）复制失败时仍然替换，由调用方记录日志
+#[cfg(unix)]
+fn copy_xattrs(from: &Path, to: &Path) -> io::Result<()> {
+    let names = match xattr::list(from) {
+        Ok(names) => names,
+        Err(e) if e.kind() == ErrorKind::Unsupported => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    for name in names {
+        if let Some(value) = xattr::get(from, &name)? {
+            xattr::set(to, &name, &value)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn copy_metadata(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::metadata(from) {
+        Ok(metadata) => fs::set_permissions(to, metadata.permissions()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    match path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        Some(dir) => File::open(dir)?.sync_all(),
+        None => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn fail_at(target: WriteStep) -> impl Fn(WriteStep) -> io::Result<()> {
+        fail_with(target, ErrorKind::StorageFull)
+    }
+
+    fn fail_with(target: WriteStep, kind: ErrorKind) -> impl Fn(WriteStep) -> io::Result<()> {
+        move |step| {
+            if step == target {
+                Err(io::Error::new(kind, format!("fail at {:?}", step)))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn file_names(dir: &Path) -> Vec<String> {
+        let mut names = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_write_atomic() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("hosts");
+        write_atomic(&path, "127.0.0.1 localhost\n")?;
+        assert_eq!(fs::read_to_string(&path)?, "127.0.0.1 localhost\n");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640))?;
+        let has_xattr = xattr::set(&path, "user.switchhosts", b"1").is_ok();
+        write_atomic(&path, "10.0.0.1 api.test\n")?;
+        assert_eq!(fs::read_to_string(&path)?, "10.0.0.1 api.test\n");
+        assert_eq!(fs::metadata(&path)?.permissions().mode() & 0o777, 0o640);
+        if has_xattr {
+            assert_eq!(xattr::get(&path, "user.switchhosts")?, Some(b"1".to_vec()));
+        }
+        assert_eq!(file_names(dir.path()), vec!["hosts"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_atomic_failures() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("config.json");
+        fs::write(&path, "old")?;
+        for step in [WriteStep::CreateTemp, WriteStep::Write, WriteStep::Sync] {
+            assert!(write_atomic_with(&path, b"new", &fail_at(step)).is_err());
+            assert_eq!(fs::read_to_string(&path)?, "old");
+            assert_eq!(file_names(dir.path()), vec!["config.json"]);
+        }
+        // 无法继承属主或权限时报错，不能退回到覆盖写入
+        assert!(write_atomic_with(&path, b"new", &fail_at(WriteStep::CopyMetadata)).is_err());
+        assert_eq!(fs::read_to_string(&path)?, "old");
+        assert_eq!(file_names(dir.path()), vec!["config.json"]);
+
+        // 只有 rename 因为 EBUSY、EXDEV 失败时才退回到直接覆盖写入
+        for kind in [ErrorKind::ResourceBusy, ErrorKind::CrossesDevices] {
+            fs::write(&path, "old")?;
+            write_atomic_with(&path, b"new", &fail_with(WriteStep::Rename, kind))?;
+            assert_eq!(fs::read_to_string(&path)?, "new");
+            assert_eq!(file_names(dir.path()), vec!["config.json"]);
+        }
+        fs::write(&path, "old")?;
+        let inject = fail_with(WriteStep::Rename, ErrorKind::PermissionDenied);
+        assert!(write_atomic_with(&path, b"new", &inject).is_err());
+        assert_eq!(fs::read_to_string(&path)?, "old");
+        assert_eq!(file_names(dir.path()), vec!["config.json"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+use chrono::Local;
+use std::{fs, path::PathBuf};
+
+use crate::atomic_write::write_atomic;
+use crate::data::{check_switch_host_rs_dir_exist, get_switch_hosts_rs_dir};
+use crate::hosts::{replace_sys_hosts_with_password, write_error};
+use crate::text_format::TextFormat;
+use crate::util::Result;
+
+const BACKUP_PREFIX: &str = "hosts-";
+
+const BACKUP_SUFFIX: &str = ".bak";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Backup {
+    pub name: String,
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+impl Backup {
+    /// 从文件名中解析出的备份时间，如 2026-10-18 15:30:00
+    pub fn created(&self) -> String {
+        let stamp = self
+            .name
+            .trim_start_matches(BACKUP_PREFIX)
+            .trim_end_matches(BACKUP_SUFFIX);
+        chrono::NaiveDateTime::parse_from_str(stamp, "%Y%m%d-%H%M%S-%3f")
+            .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or(stamp.to_owned())
+    }
+}
+
+pub fn get_backup_dir() -> Option<PathBuf> {
+    get_switch_hosts_rs_dir().map(|buf| buf.join("backups"))
+}
+
+pub fn check_backup_dir_exist() -> Result<()> {
+    check_switch_host_rs_dir_exist()?;
+    let dir = get_backup_dir().unwrap();
+    if !fs::exists(&dir)? {
+        fs::create_dir(&dir)?;
+    }
+    Ok(())
+}
+
+/// 所有备份，最新的在前面
+pub fn list_backups() -> Result<Vec<Backup>> {
+    check_backup_dir_exist()?;
+    let mut backups = vec![];
+    for entry in fs::read_dir(get_backup_dir().unwrap())? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with(BACKUP_PREFIX) && name.ends_with(BACKUP_SUFFIX) {
+            backups.push(Backup {
+                name,
+                path: entry.path(),
+                size: entry.metadata()?.len(),
+            });
+        }
+    }
+    backups.sort_by(|a, b| b.name.cmp(&a.name));
+    Ok(backups)
+}
+
+pub fn find_backup(name: &str) -> Result<Backup> {
+    list_backups()?
+        .into_iter()
+        .find(|backup| backup.name == name)
+        .ok_or(color_eyre::eyre::Error::msg("not found backup"))
+}
+
+pub fn read_backup(name: &str) -> Result<String> {
+    let buf = fs::read(find_backup(name)?.path)?;
+    Ok(TextFormat::detect(&buf).decode(&buf))
+}
+
+/// 备份一份 hosts 内容，与最近一次备份相同时不重复备份。备份后只保留最近 `retention` 份
+pub fn create_backup(content: &[u8], retention: usize) -> Result<Option<Backup>> {
+    let backups = list_backups()?;
+    if let Some(latest) = backups.first() {
+        if fs::read(&latest.path)? == content {
+            return Ok(None);
+        }
+    }
+    let name = format!(
+        "{}{}{}",
+        BACKUP_PREFIX,
+        Local::now().format("%Y%m%d-%H%M%S-%3f"),
+        BACKUP_SUFFIX
+    );
+    let path = get_backup_dir().unwrap().join(&name);
+    write_atomic(&path, content)?;
+    prune_backups(retention)?;
+    Ok(Some(Backup {
+        name,
+        path,
+        size: content.len() as u64,
+    }))
+}
+
+pub fn prune_backups(retention: usize) -> Result<()> {
+    for backup in list_backups()?.iter().skip(retention.max(1)) {
+        fs::remove_file(&backup.path)?;
+    }
+    Ok(())
+}
+
+/// 用备份覆盖系统 hosts，覆盖前会先备份当前内容
+pub fn restore_backup(name: &str, password: Option<&str>) -> Result<()> {
+    let content = fs::read(find_backup(name)?.path)?;
+    replace_sys_hosts_with_password(password, content).map_err(|e| write_error(name, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hosts::get_sys_hosts_path;
+    use crate::paths::with_temp_data_dir;
+    use crate::settings::{write_settings, Settings};
+    use std::{thread, time::Duration};
+
+    /// 备份名称精确到毫秒，连续创建时间隔一下，避免名称相同
+    fn create(content: &str, retention: usize) -> Result<Option<Backup>> {
+        thread::sleep(Duration::from_millis(2));
+        create_backup(content.as_bytes(), retention)
+    }
+
+    #[test]
+    fn test_create_backup() -> Result<()> {
+        with_temp_data_dir(|_| {
+            let first = create("10.0.0.1 a.test\n", 20)?.unwrap();
+            assert_eq!(fs::read_to_string(&first.path)?, "10.0.0.1 a.test\n");
+            // 与最近一次备份相同时不重复备份
+            assert_eq!(create("10.0.0.1 a.test\n", 20)?, None);
+            let second = create("10.0.0.2 b.test\n", 20)?.unwrap();
+            assert_eq!(list_backups()?, vec![second.clone(), first]);
+            // 只和最近一次比较，与更早的备份相同时仍然备份
+            assert!(create("10.0.0.1 a.test\n", 20)?.is_some());
+            assert_eq!(list_backups()?.len(), 3);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_prune_backups() -> Result<()> {
+        with_temp_data_dir(|_| {
+            for i in 0..5 {
+                create(&format!("10.0.0.{i} a.test\n"), 3)?;
+            }
+            let backups = list_backups()?;
+            assert_eq!(backups.len(), 3);
+            assert_eq!(read_backup(&backups[0].name)?, "10.0.0.4 a.test\n");
+            assert_eq!(read_backup(&backups[2].name)?, "10.0.0.2 a.test\n");
+
+            prune_backups(1)?;
+            assert_eq!(list_backups()?, vec![backups[0].clone()]);
+            // 至少保留一份
+            prune_backups(0)?;
+            assert_eq!(list_backups()?.len(), 1);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_restore_backup() -> Result<()> {
+        with_temp_data_dir(|dir| {
+            let hosts = dir.join("hosts");
+            write_settings(&Settings {
+                hosts_file: Some(hosts.to_string_lossy().into_owned()),
+                ..Settings::default()
+            })?;
+            assert_eq!(get_sys_hosts_path(), hosts.to_string_lossy());
+
+            let old = "\u{feff}127.0.0.1 localhost\r\n10.0.0.1 a.test\r\n";
+            let backup = create(old, 20)?.unwrap();
+            fs::write(&hosts, "127.0.0.1 localhost\n10.0.0.2 b.test\n")?;
+            // 恢复前的备份与上面的备份不能在同一毫秒内创建
+            thread::sleep(Duration::from_millis(2));
+            restore_backup(&backup.name, None)?;
+            // 按字节原样恢复，BOM 和换行符不变
+            assert_eq!(fs::read_to_string(&hosts)?, old);
+            // 写入失败不是权限问题时返回原来的错误，不会要求输入密码
+            let missing = dir.join("missing/hosts");
+            write_settings(&Settings {
+                hosts_file: Some(missing.to_string_lossy().into_owned()),
+                ..Settings::default()
+            })?;
+            let e = restore_backup(&backup.name, None).unwrap_err();
+            assert_ne!(e.to_string(), "no permission");
+            write_settings(&Settings {
+                hosts_file: Some(hosts.to_string_lossy().into_owned()),
+                ..Settings::default()
+            })?;
+
+            // 恢复前的内容也被备份
+            let backups = list_backups()?;
+            assert_eq!(backups.len(), 2);
+            assert_eq!(
+                read_backup(&backups[0].name)?,
+                "127.0.0.1 localhost\n10.0.0.2 b.test\n"
+            );
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_backup_created() {
+        let backup = Backup {
+            name: "hosts-20261018-153000-123.bak".to_owned(),
+            path: PathBuf::new(),
+            size: 0,
+        };
+        assert_eq!(backup.created(), "2026-10-18 15:30:00");
+    }
+}
@@ -0,0 +1,100 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use log::error;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Row, StatefulWidget, Table, TableState},
+};
+
+use crate::backup::{list_backups, Backup};
+
+#[derive(Debug, Default)]
+pub struct BackupList {
+    backups: Vec<Backup>,
+    state: TableState,
+}
+
+impl BackupList {
+    pub fn new() -> Self {
+        BackupList::default()
+    }
+
+    pub fn reload(&mut self) {
+        self.backups = list_backups().unwrap_or_else(|e| {
+            error!("{e}");
+            vec![]
+        });
+        self.state
+            .select(if self.backups.is_empty() { None } else { Some(0) });
+    }
+
+    pub fn get_selected(&self) -> Option<&Backup> {
+        self.state.selected().and_then(|idx| self.backups.get(idx))
+    }
+
+    /// 返回 Some(true) 表示查看选中的备份，Some(false) 表示关闭
+    pub fn handle_event(&mut self, event: KeyEvent) -> Option<bool> {
+        match event.code {
+            KeyCode::Enter | KeyCode::Right => {
+                if self.get_selected().is_some() {
+                    Some(true)
+                } else {
+                    None
+                }
+            }
+            KeyCode::Esc => Some(false),
+            KeyCode::Up => {
+                self.state.select_previous();
+                None
+            }
+            KeyCode::Down => {
+                if self.state.selected().unwrap_or(0) + 1 < self.backups.len() {
+                    self.state.select_next();
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    pub fn draw(&mut self, area: Rect, buf: &mut Buffer) {
+        let strong_style = Style::default()
+            .add_modifier(Modifier::BOLD)
+            .fg(Color::LightGreen);
+        let footer = Line::from(vec![
+            Span::styled(" Enter", strong_style),
+            Span::raw(" 查看差异并恢复 "),
+            Span::styled("Esc", strong_style),
+            Span::raw(" 关闭 "),
+        ]);
+        let block = Block::bordered()
+            .style(Style::new().white().on_black())
+            .title("系统 hosts 备份")
+            .title_bottom(footer);
+        let rows = self
+            .backups
+            .iter()
+            .map(|backup| {
+                Row::new(vec![
+                    backup.created(),
+                    format!("{} B", backup.size),
+                    backup.name.clone(),
+                ])
+            })
+            .collect::<Vec<_>>();
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(20),
+                Constraint::Length(10),
+                Constraint::Fill(1),
+            ],
+        )
+        .header(Row::new(vec!["时间", "大小", "文件"]).height(1))
+        .block(block)
+        .row_highlight_style(Style::default().bg(Color::DarkGray));
+        StatefulWidget::render(table, area, buf, &mut self.state);
+    }
+}
@@ -0,0 +1,842 @@
+use crate::backup::{find_backup, list_backups, read_backup, restore_backup};
+use crate::data::{read_item_data, ConfigItem};
+use crate::diff::unified_diff;
+use crate::hosts::{is_unsafe_write, read_sys_hosts};
+use crate::list::{partially_written, remove_target_block, HostsList};
+use crate::paths::PathOverrides;
+use crate::remote::fetch_remote;
+use crate::settings::read_settings;
+use crate::target::{
+    find_target, get_targets, invalid_marker_message, invalid_markers, SYSTEM_TARGET,
+};
+use crate::util::Result;
+use crate::validator::validate_hosts;
+use log::error;
+use std::fs;
+use std::io::{self, Write};
+
+pub const EXIT_OK: i32 = 0;
+
+pub const EXIT_FAILURE: i32 = 1;
+
+pub const EXIT_USAGE: i32 = 2;
+
+pub const EXIT_NOT_FOUND: i32 = 3;
+
+pub const EXIT_NO_PERMISSION: i32 = 4;
+
+pub const EXIT_INVALID: i32 = 5;
+
+pub const EXIT_CONFLICT: i32 = 6;
+
+const USAGE: &str = "用法: switchhosts-rs [命令] [选项]
+
+不带命令时启动交互界面。
+
+命令:
+  list                    列出所有 hosts
+  status                  列出已启用的 hosts
+  on <标题|id>            启用 hosts
+  off <标题|id>           停用 hosts
+  toggle <标题|id>        切换 hosts 的启用状态
+  exclusive <标题|id> [<集合>]
+                          设置 hosts 所属的互斥集合，不指定集合时移出
+  targets                 列出可以写入的目标
+  targets <标题|id> [<目标>...]
+                          设置 hosts 写入的目标，不指定目标时只写入系统 hosts
+  add-remote <标题> <url> 添加远程 hosts 并立即拉取
+  refresh [<标题|id>]     立即刷新远程 hosts，不指定时刷新全部
+  backup list             列出系统 hosts 的备份
+  backup diff <备份>      查看备份与当前系统 hosts 的差异
+  backup restore <备份>   用备份覆盖系统 hosts
+  remove-block [<目标>]   移除受管理区块（卸载前使用），不指定目标时移除所有目标中的区块
+  export [<标题|id>...]   输出组合后的 hosts，不写入系统 hosts，不指定时组合已启用的 hosts
+  help                    显示帮助
+
+选项:
+  --force                 存在主机名冲突时仍然写入
+  --dry-run               只输出系统 hosts 的变化，不写入
+  --interval <秒>         远程 hosts 的自动刷新间隔，0 表示不自动刷新
+  --full                  export 输出写入后的完整系统 hosts，而不只是受管理区块的内容
+  --provenance            export 保留每个 hosts 的来源注释（含修改时间，每次导出的内容可能不同）
+  --output <路径>         export 输出到文件，不指定或为 - 时输出到标准输出
+
+全局选项（交互界面同样可用）:
+  --data-dir <目录>       数据目录，也可以用环境变量 SWITCHHOSTS_RS_DATA_DIR 指定
+  --hosts-file <路径>     系统 hosts 的路径，也可以用环境变量 SWITCHHOSTS_RS_HOSTS_FILE 指定
+
+退出码:
+  0 成功  1 失败  2 参数错误  3 未找到 hosts  4 没有写入 Hosts 文件的权限
+  5 hosts 内容校验不通过  6 存在主机名冲突";
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    List,
+    Status,
+    On(String),
+    Off(String),
+    Toggle(String),
+    Exclusive(String, Option<String>),
+    Targets(Option<String>, Vec<String>),
+    AddRemote(String, String),
+    Refresh(Option<String>),
+    BackupList,
+    BackupDiff(String),
+    BackupRestore(String),
+    RemoveBlock(Option<String>),
+    Export(Vec<String>),
+    Help,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Options {
+    pub force: bool,
+    pub dry_run: bool,
+    pub interval: Option<u64>,
+    pub full: bool,
+    pub provenance: bool,
+    pub output: Option<String>,
+}
+
+/// 取出 --data-dir 和 --hosts-file，剩下的参数交给 parse_args，没有剩余参数时启动交互界面
+pub fn take_path_options(
+    args: Vec<String>,
+) -> std::result::Result<(PathOverrides, Vec<String>), String> {
+    let mut overrides = PathOverrides::default();
+    let mut rest = vec![];
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        let target = match arg.as_str() {
+            "--data-dir" => &mut overrides.data_dir,
+            "--hosts-file" => &mut overrides.hosts_file,
+            _ => {
+                rest.push(arg);
+                continue;
+            }
+        };
+        let value = iter
+            .next()
+            .filter(|value| !value.is_empty())
+            .ok_or(format!("{} 需要一个路径", arg))?;
+        *target = Some(value.into());
+    }
+    Ok((overrides, rest))
+}
+
+pub fn parse_args(args: &[String]) -> std::result::Result<(Command, Options), String> {
+    let mut options = Options::default();
+    let mut positional = vec![];
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--force" | "-f" => options.force = true,
+            "--dry-run" | "-n" => options.dry_run = true,
+            "--interval" => {
+                let interval = iter
+                    .next()
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .ok_or(String::from("--interval 需要一个秒数"))?;
+                options.interval = Some(interval);
+            }
+            "--full" => options.full = true,
+            "--provenance" => options.provenance = true,
+            "--output" | "-o" => {
+                let output = iter
+                    .next()
+                    .filter(|value| !value.is_empty())
+                    .ok_or(String::from("--output 需要一个路径"))?;
+                options.output = Some(output.to_owned());
+            }
+            "-h" | "--help" => positional.push(arg.as_str()),
+            flag if flag.starts_with('-') => return Err(format!("未知选项 {}", flag)),
+            _ => positional.push(arg.as_str()),
+        }
+    }
+    let name = *positional.first().ok_or(String::from("缺少命令"))?;
+    let target = || {
+        positional
+            .get(1)
+            .map(|target| target.to_string())
+            .ok_or(format!("命令 {} 缺少参数 <标题|id>", name))
+    };
+    let command = match name {
+        "list" | "ls" => Command::List,
+        "status" => Command::Status,
+        "on" => Command::On(target()?),
+        "off" => Command::Off(target()?),
+        "toggle" => Command::Toggle(target()?),
+        "exclusive" => Command::Exclusive(target()?, positional.get(2).map(|set| set.to_string())),
+        "targets" => Command::Targets(
+            positional.get(1).map(|target| target.to_string()),
+            positional
+                .iter()
+                .skip(2)
+                .map(|name| name.to_string())
+                .collect(),
+        ),
+        "add-remote" => match (positional.get(1), positional.get(2)) {
+            (Some(title), Some(url)) => Command::AddRemote(title.to_string(), url.to_string()),
+            _ => return Err(String::from("命令 add-remote 缺少参数 <标题> <url>")),
+        },
+        "refresh" => Command::Refresh(positional.get(1).map(|target| target.to_string())),
+        "backup" => {
+            let backup = || {
+                positional
+                    .get(2)
+                    .map(|name| name.to_string())
+                    .ok_or(String::from("缺少参数 <备份>"))
+            };
+            match positional.get(1).copied() {
+                Some("list") | None => Command::BackupList,
+                Some("diff") => Command::BackupDiff(backup()?),
+                Some("restore") => Command::BackupRestore(backup()?),
+                Some(sub) => return Err(format!("未知命令 backup {}", sub)),
+            }
+        }
+        "remove-block" => Command::RemoveBlock(positional.get(1).map(|name| name.to_string())),
+        "export" => Command::Export(
+            positional
+                .iter()
+                .skip(1)
+                .map(|target| target.to_string())
+                .collect(),
+        ),
+        "help" | "-h" | "--help" => Command::Help,
+        _ => return Err(format!("未知命令 {}", name)),
+    };
+    Ok((command, options))
+}
+
+pub fn run(args: Vec<String>) -> i32 {
+    let (command, options) = match parse_args(&args) {
+        Ok(parsed) => parsed,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            return EXIT_USAGE;
+        }
+    };
+    let mut hosts_list = HostsList::new();
+    hosts_list.init();
+    if let Some(e) = hosts_list.load_error() {
+        eprintln!("无法读取配置文件: {}", e);
+        return EXIT_FAILURE;
+    }
+    if command != Command::Help && !invalid_markers().is_empty() {
+        eprintln!("{}", invalid_marker_message());
+        return EXIT_FAILURE;
+    }
+    match command {
+        Command::Help => {
+            println!("{}", USAGE);
+            EXIT_OK
+        }
+        Command::List => {
+            for item in user_items(&hosts_list) {
+                print_item(item);
+            }
+            EXIT_OK
+        }
+        Command::Status => {
+            for item in user_items(&hosts_list).filter(|item| item.is_on()) {
+                print_item(item);
+            }
+            EXIT_OK
+        }
+        Command::On(target) => switch(&mut hosts_list, &target, Some(true), &options),
+        Command::Off(target) => switch(&mut hosts_list, &target, Some(false), &options),
+        Command::Toggle(target) => switch(&mut hosts_list, &target, None, &options),
+        Command::Exclusive(target, exclusive_set) => {
+            exclusive(&mut hosts_list, &target, exclusive_set)
+        }
+        Command::Targets(None, _) => {
+            for target in get_targets() {
+                println!("{}\t{}", target.name, target.path);
+            }
+            EXIT_OK
+        }
+        Command::Targets(Some(target), names) => set_targets(&mut hosts_list, &target, names),
+        Command::AddRemote(title, url) => add_remote(&mut hosts_list, title, url, &options),
+        Command::Refresh(target) => refresh(&mut hosts_list, target.as_deref()),
+        Command::BackupList => match list_backups() {
+            Ok(backups) => {
+                for backup in backups {
+                    println!("{}\t{} B\t{}", backup.created(), backup.size, backup.name);
+                }
+                EXIT_OK
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                EXIT_FAILURE
+            }
+        },
+        Command::BackupDiff(name) => backup_diff(&name),
+        Command::BackupRestore(name) => backup_restore(&name, &options),
+        Command::RemoveBlock(name) => remove_block(name.as_deref()),
+        Command::Export(targets) => export(&hosts_list, &targets, &options),
+    }
+}
+
+/// 输出组合后的 hosts，不写入任何 hosts 文件
+fn export(hosts_list: &HostsList, targets: &[String], options: &Options) -> i32 {
+    let mut ids = vec![];
+    for target in targets {
+        match find_item(hosts_list, target) {
+            Ok(item) => ids.push(item.id().to_owned()),
+            Err(e) if e.to_string() == "ambiguous title" => {
+                eprintln!("存在多个标题为 {} 的 hosts，请使用 id", target);
+                return EXIT_USAGE;
+            }
+            Err(_) => {
+                eprintln!("未找到 hosts: {}", target);
+                return EXIT_NOT_FOUND;
+            }
+        }
+    }
+    let ids = Some(ids.as_slice()).filter(|ids| !ids.is_empty());
+    let content = match hosts_list.export_content(ids, options.full, options.provenance) {
+        Ok(content) => content,
+        Err(e) if e.to_string() == "unreadable hosts" => {
+            eprintln!("无法读取 Hosts 文件，无法生成完整内容");
+            return EXIT_FAILURE;
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            return EXIT_FAILURE;
+        }
+    };
+    match options.output.as_deref() {
+        None | Some("-") => match io::stdout().write_all(&content) {
+            Ok(_) => EXIT_OK,
+            Err(e) => {
+                eprintln!("{}", e);
+                EXIT_FAILURE
+            }
+        },
+        Some(path) => match fs::write(path, &content) {
+            Ok(_) => {
+                println!("已导出到 {}", path);
+                EXIT_OK
+            }
+            Err(e) => {
+                eprintln!("无法写入 {}: {}", path, e);
+                EXIT_FAILURE
+            }
+        },
+    }
+}
+
+/// 移除受管理区块，配置中的启用状态不变
+fn remove_block(name: Option<&str>) -> i32 {
+    let targets = match name {
+        Some(name) => match find_target(name) {
+            Some(target) => vec![target],
+            None => {
+                eprintln!("未知目标: {}", name);
+                return EXIT_USAGE;
+            }
+        },
+        None => get_targets(),
+    };
+    let mut code = EXIT_OK;
+    for target in targets {
+        match remove_target_block(&target, None) {
+            Ok(true) => println!("已移除 {} 中的受管理区块", target.name),
+            Ok(false) => println!("{} 中没有受管理区块", target.name),
+            Err(e) if e.to_string() == "no permission" => {
+                eprintln!("没有写入 {} 的权限，{}", target.path, permission_hint());
+                code = EXIT_NO_PERMISSION;
+            }
+            Err(e) => {
+                if is_unsafe_write(&e) {
+                    eprintln!("{}: {}", target.name, unsafe_write_message(&e));
+                } else {
+                    eprintln!("{}: {}", target.name, e);
+                }
+                if code == EXIT_OK {
+                    code = EXIT_FAILURE;
+                }
+            }
+        }
+    }
+    code
+}
+
+fn backup_diff(name: &str) -> i32 {
+    if find_backup(name).is_err() {
+        eprintln!("未找到备份: {}", name);
+        return EXIT_NOT_FOUND;
+    }
+    match read_sys_hosts().and_then(|current| read_backup(name).map(|backup| (current, backup))) {
+        Ok((current, backup)) => {
+            print!("{}", unified_diff(&current, &backup, "当前 hosts", name));
+            EXIT_OK
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+fn backup_restore(name: &str, options: &Options) -> i32 {
+    if options.dry_run {
+        return backup_diff(name);
+    }
+    if find_backup(name).is_err() {
+        eprintln!("未找到备份: {}", name);
+        return EXIT_NOT_FOUND;
+    }
+    match restore_backup(name, None) {
+        Ok(_) => {
+            println!("已恢复备份 {}", name);
+            EXIT_OK
+        }
+        Err(e) if e.to_string() == "no permission" => {
+            eprintln!("没有写入 Hosts 文件的权限，{}", permission_hint());
+            EXIT_NO_PERMISSION
+        }
+        Err(e) if is_unsafe_write(&e) => {
+            eprintln!("{}", unsafe_write_message(&e));
+            EXIT_FAILURE
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// 没有权限时说明怎样用当前的提权方式获得权限
+fn permission_hint() -> &'static str {
+    read_settings().privileged_writer().permission_hint()
+}
+
+/// 写入前的安全检查没有通过时的提示
+fn unsafe_write_message(e: &color_eyre::eyre::Error) -> &'static str {
+    if e.to_string() == "unreadable hosts" {
+        "无法读取 Hosts 文件，已停止写入以免覆盖原有内容"
+    } else {
+        "写入后 Hosts 文件会缺少 localhost 条目，已停止写入"
+    }
+}
+
+/// 写入中途失败且无法恢复时的提示
+fn partially_written_message(e: &color_eyre::eyre::Error) -> String {
+    format!(
+        "写入中途失败，{} 已写入且无法恢复为原来的内容，请检查这些文件",
+        partially_written(e).unwrap_or_default()
+    )
+}
+
+fn user_items(hosts_list: &HostsList) -> impl Iterator<Item = &ConfigItem> {
+    hosts_list
+        .get_all_hosts_item_list()
+        .iter()
+        .filter(|item| item.id() != "system")
+}
+
+fn print_item(item: &ConfigItem) {
+    let mark = if item.is_on() { "✓" } else { " " };
+    let mark = match item.exclusive_set() {
+        Some(exclusive_set) => format!("{} [互斥: {}]", mark, exclusive_set),
+        None => mark.to_owned(),
+    };
+    let targets = item.targets();
+    let targets = if targets == [SYSTEM_TARGET] {
+        String::new()
+    } else {
+        format!("\t[目标: {}]", targets.join(", "))
+    };
+    match item.remote() {
+        Some(remote) => println!(
+            "{} {}\t{}\t{} {}{}",
+            mark,
+            item.title(),
+            item.id(),
+            remote.url,
+            remote.status(),
+            targets
+        ),
+        None if item.is_group() => println!(
+            "{} {}\t{}\t[分组 {} 项]{}",
+            mark,
+            item.title(),
+            item.id(),
+            item.members().len(),
+            targets
+        ),
+        None => println!("{} {}\t{}{}", mark, item.title(), item.id(), targets),
+    }
+}
+
+/// 修改写入的目标后，已启用的 hosts 立即重新写入，从目标中移除的文件也会被清理
+fn set_targets(hosts_list: &mut HostsList, target: &str, names: Vec<String>) -> i32 {
+    let (id, applied) = match find_item(hosts_list, target) {
+        Ok(item) => (item.id().to_owned(), hosts_list.is_applied(item.id())),
+        Err(e) => {
+            eprintln!("未找到 hosts: {} ({})", target, e);
+            return EXIT_NOT_FOUND;
+        }
+    };
+    if let Some(name) = names.iter().find(|name| find_target(name).is_none()) {
+        eprintln!("未知目标: {}，请先在设置中添加", name);
+        return EXIT_USAGE;
+    }
+    hosts_list.set_selected_item(id);
+    if let Err(e) = hosts_list.set_targets(names) {
+        eprintln!("{}", e);
+        return EXIT_FAILURE;
+    }
+    if !applied {
+        return EXIT_OK;
+    }
+    match hosts_list.apply_enabled(None) {
+        Ok(_) => EXIT_OK,
+        Err(e) if e.to_string() == "no permission" => {
+            eprintln!("没有写入 Hosts 文件的权限，{}", permission_hint());
+            EXIT_NO_PERMISSION
+        }
+        Err(e) if is_unsafe_write(&e) => {
+            eprintln!("{}", unsafe_write_message(&e));
+            EXIT_FAILURE
+        }
+        Err(e) if partially_written(&e).is_some() => {
+            eprintln!("{}", partially_written_message(&e));
+            EXIT_FAILURE
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+fn exclusive(hosts_list: &mut HostsList, target: &str, exclusive_set: Option<String>) -> i32 {
+    let id = match find_item(hosts_list, target) {
+        Ok(item) => item.id().to_owned(),
+        Err(e) => {
+            eprintln!("未找到 hosts: {} ({})", target, e);
+            return EXIT_NOT_FOUND;
+        }
+    };
+    hosts_list.set_selected_item(id);
+    match hosts_list.set_exclusive_set(exclusive_set) {
+        Ok(_) => EXIT_OK,
+        Err(e) if e.to_string() == "exclusive set conflict" => {
+            eprintln!("该互斥集合中已有启用的 hosts，请先停用");
+            EXIT_FAILURE
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+fn add_remote(hosts_list: &mut HostsList, title: String, url: String, options: &Options) -> i32 {
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        eprintln!("请输入 http:// 或 https:// 开头的地址: {}", url);
+        return EXIT_USAGE;
+    }
+    let interval = options
+        .interval
+        .unwrap_or(read_settings().remote_refresh_interval);
+    match hosts_list.add_remote_item(title, url, interval) {
+        Ok(id) => {
+            println!("{}", id);
+            refresh(hosts_list, Some(&id))
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// 同步拉取远程 hosts，已启用的 hosts 内容有变化时重新写入系统 hosts
+fn refresh(hosts_list: &mut HostsList, target: Option<&str>) -> i32 {
+    let remotes = match target {
+        Some(target) => match find_item(hosts_list, target) {
+            Ok(item) if item.remote().is_some() => vec![item.clone()],
+            Ok(item) => {
+                eprintln!("{} 不是远程 hosts", item.title());
+                return EXIT_USAGE;
+            }
+            Err(e) => {
+                eprintln!("未找到 hosts: {} ({})", target, e);
+                return EXIT_NOT_FOUND;
+            }
+        },
+        None => hosts_list.get_remote_items().into_iter().cloned().collect(),
+    };
+    let mut code = EXIT_OK;
+    for item in remotes {
+        let remote = item.remote().unwrap();
+        let res = fetch_remote(
+            &remote.url,
+            remote.etag.as_deref(),
+            remote.last_modified.as_deref(),
+        );
+        if let Err(e) = &res {
+            eprintln!("拉取 {} 失败: {}", item.title(), e);
+        }
+        match hosts_list.apply_remote_result(item.id(), res, None) {
+            Ok(_) => {}
+            Err(e) if e.to_string() == "no permission" => {
+                eprintln!("没有写入 Hosts 文件的权限，{}", permission_hint());
+                code = EXIT_NO_PERMISSION;
+            }
+            Err(e) if is_unsafe_write(&e) => {
+                eprintln!("{}", unsafe_write_message(&e));
+                code = EXIT_FAILURE;
+            }
+            Err(e) if partially_written(&e).is_some() => {
+                eprintln!("{}", partially_written_message(&e));
+                code = EXIT_FAILURE;
+            }
+            Err(e) => {
+                if e.to_string() != "fetch failed" {
+                    eprintln!("{}", e);
+                }
+                if code == EXIT_OK {
+                    code = EXIT_FAILURE;
+                }
+            }
+        }
+    }
+    code
+}
+
+/// 查找标题或 id 匹配的 hosts，id 优先
+pub fn find_item<'a>(hosts_list: &'a HostsList, target: &str) -> Result<&'a ConfigItem> {
+    if let Some(item) = user_items(hosts_list).find(|item| item.id() == target) {
+        return Ok(item);
+    }
+    let matched = user_items(hosts_list)
+        .filter(|item| item.title() == target)
+        .collect::<Vec<_>>();
+    match matched.len() {
+        0 => Err(color_eyre::eyre::Error::msg("not found config")),
+        1 => Ok(matched[0]),
+        _ => Err(color_eyre::eyre::Error::msg("ambiguous title")),
+    }
+}
+
+/// `on` 为 None 时切换状态，否则切换到指定状态
+fn switch(hosts_list: &mut HostsList, target: &str, on: Option<bool>, options: &Options) -> i32 {
+    let item = match find_item(hosts_list, target) {
+        Ok(item) => item,
+        Err(e) if e.to_string() == "ambiguous title" => {
+            eprintln!("存在多个标题为 {} 的 hosts，请使用 id", target);
+            return EXIT_USAGE;
+        }
+        Err(_) => {
+            eprintln!("未找到 hosts: {}", target);
+            return EXIT_NOT_FOUND;
+        }
+    };
+    let (id, title, is_on) = (item.id().to_owned(), item.title().to_owned(), item.is_on());
+    if on == Some(is_on) {
+        println!(
+            "{} 已经是{}状态",
+            title,
+            if is_on { "启用" } else { "停用" }
+        );
+        return EXIT_OK;
+    }
+    hosts_list.set_selected_item(id.clone());
+    match hosts_list.check_selected_conflicts(false) {
+        Ok(conflicts) if !conflicts.is_empty() => {
+            for line in conflicts.iter().flat_map(|c| c.describe()) {
+                eprintln!("{}", line);
+            }
+            if !options.force {
+                eprintln!("检测到主机名冲突，使用 --force 仍然写入");
+                return EXIT_CONFLICT;
+            }
+        }
+        Ok(_) => {}
+        Err(e) => error!("{e}"),
+    }
+    if options.dry_run {
+        return match hosts_list.preview_toggle() {
+            Ok((current, toggled)) => {
+                print!(
+                    "{}",
+                    unified_diff(&current, &toggled, "当前 hosts", "切换后 hosts")
+                );
+                EXIT_OK
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                EXIT_FAILURE
+            }
+        };
+    }
+    let enabled_before = user_items(hosts_list)
+        .filter(|item| item.is_on())
+        .map(|item| item.id().to_owned())
+        .collect::<Vec<_>>();
+    match hosts_list.toggle_on_off(None, false) {
+        Ok(_) => {
+            println!("{} 已{}", title, if is_on { "停用" } else { "启用" });
+            for item in user_items(hosts_list).filter(|item| {
+                item.id() != &id && !item.is_on() && enabled_before.contains(item.id())
+            }) {
+                println!("{} 已停用（互斥集合）", item.title());
+            }
+            EXIT_OK
+        }
+        Err(e) if e.to_string() == "no permission" => {
+            eprintln!("没有写入 Hosts 文件的权限，{}", permission_hint());
+            EXIT_NO_PERMISSION
+        }
+        Err(e) if is_unsafe_write(&e) => {
+            eprintln!("{}", unsafe_write_message(&e));
+            EXIT_FAILURE
+        }
+        Err(e) if partially_written(&e).is_some() => {
+            eprintln!("{}", partially_written_message(&e));
+            EXIT_FAILURE
+        }
+        Err(e) if e.to_string() == "invalid hosts" => {
+            for diagnostic in validate_hosts(&read_item_data(&id).unwrap_or_default()) {
+                eprintln!("第 {} 行: {}", diagnostic.line_number, diagnostic.message);
+            }
+            eprintln!("{} 内容校验不通过，已阻止启用", title);
+            EXIT_INVALID
+        }
+        Err(e) => {
+            error!("{e}");
+            eprintln!("{}", e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_take_path_options() {
+        assert_eq!(
+            take_path_options(args(&[
+                "--data-dir",
+                "/tmp/sh",
+                "list",
+                "--hosts-file",
+                "hosts"
+            ])),
+            Ok((
+                PathOverrides {
+                    data_dir: Some("/tmp/sh".into()),
+                    hosts_file: Some("hosts".into()),
+                },
+                args(&["list"])
+            ))
+        );
+        assert_eq!(
+            take_path_options(args(&["--hosts-file", "hosts"])),
+            Ok((
+                PathOverrides {
+                    data_dir: None,
+                    hosts_file: Some("hosts".into()),
+                },
+                vec![]
+            ))
+        );
+        assert!(take_path_options(args(&["list", "--data-dir"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(
+            parse_args(&args(&["list"])),
+            Ok((Command::List, Options::default()))
+        );
+        assert_eq!(
+            parse_args(&args(&["on", "--force", "staging"])),
+            Ok((
+                Command::On("staging".to_owned()),
+                Options {
+                    force: true,
+                    ..Default::default()
+                }
+            ))
+        );
+        assert!(parse_args(&args(&["off"])).is_err());
+        assert!(parse_args(&args(&["unknown"])).is_err());
+        assert!(parse_args(&args(&["list", "--unknown"])).is_err());
+        assert_eq!(
+            parse_args(&args(&[
+                "add-remote",
+                "team",
+                "https://example.com/hosts",
+                "--interval",
+                "60"
+            ])),
+            Ok((
+                Command::AddRemote("team".to_owned(), "https://example.com/hosts".to_owned()),
+                Options {
+                    interval: Some(60),
+                    ..Default::default()
+                }
+            ))
+        );
+        assert!(parse_args(&args(&["refresh", "--interval"])).is_err());
+        assert_eq!(
+            parse_args(&args(&["exclusive", "dev", "env"])),
+            Ok((
+                Command::Exclusive("dev".to_owned(), Some("env".to_owned())),
+                Options::default()
+            ))
+        );
+        assert_eq!(
+            parse_args(&args(&["targets", "dev", "system", "web"])),
+            Ok((
+                Command::Targets(
+                    Some("dev".to_owned()),
+                    vec!["system".to_owned(), "web".to_owned()]
+                ),
+                Options::default()
+            ))
+        );
+        assert_eq!(
+            parse_args(&args(&["backup", "restore", "hosts-1.bak"])),
+            Ok((
+                Command::BackupRestore("hosts-1.bak".to_owned()),
+                Options::default()
+            ))
+        );
+        assert_eq!(
+            parse_args(&args(&["remove-block"])),
+            Ok((Command::RemoveBlock(None), Options::default()))
+        );
+        assert_eq!(
+            parse_args(&args(&[
+                "export",
+                "dev",
+                "a1",
+                "--full",
+                "--provenance",
+                "-o",
+                "hosts.out"
+            ])),
+            Ok((
+                Command::Export(vec!["dev".to_owned(), "a1".to_owned()]),
+                Options {
+                    full: true,
+                    provenance: true,
+                    output: Some("hosts.out".to_owned()),
+                    ..Default::default()
+                }
+            ))
+        );
+        assert!(parse_args(&args(&["export", "--output"])).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+
+use crate::hosts_parser::HostsDocument;
+
+/// 参与冲突检测的一份 hosts 内容
+#[derive(Clone, Debug)]
+pub struct HostsSource {
+    pub id: String,
+    pub title: String,
+    pub content: String,
+}
+
+impl HostsSource {
+    pub fn new(id: impl Into<String>, title: impl Into<String>, content: impl Into<String>) -> Self {
+        HostsSource {
+            id: id.into(),
+            title: title.into(),
+            content: content.into(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mapping {
+    pub id: String,
+    pub title: String,
+    pub line_number: usize,
+    pub ip: String,
+}
+
+/// 同一个主机名在同一地址族下被映射到了不同的 IP
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conflict {
+    pub hostname: String,
+    pub mappings: Vec<Mapping>,
+}
+
+impl Conflict {
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = vec![format!("{}:", self.hostname)];
+        for mapping in &self.mappings {
+            lines.push(format!(
+                "  {} ({} 第 {} 行)",
+                mapping.ip, mapping.title, mapping.line_number
+            ));
+        }
+        lines
+    }
+}
+
+/// IPv4 和 IPv6 分开比较，`127.0.0.1 localhost` 与 `::1 localhost` 不算冲突。
+/// `system_id` 对应的来源之间的冲突不上报，只关心受管理的 hosts 引入的冲突
+pub fn find_conflicts(sources: &[HostsSource], system_id: &str) -> Vec<Conflict> {
+    let mut groups: BTreeMap<(String, bool), Vec<Mapping>> = BTreeMap::new();
+    for source in sources {
+        let doc = HostsDocument::parse(&source.content);
+        for (line, entry) in doc.active_entries() {
+            let is_v6 = entry.ip.contains(':');
+            for hostname in &entry.hostnames {
+                groups
+                    .entry((hostname.to_lowercase(), is_v6))
+                    .or_default()
+                    .push(Mapping {
+                        id: source.id.clone(),
+                        title: source.title.clone(),
+                        line_number: line.line_number,
+                        ip: entry.ip.clone(),
+                    });
+            }
+        }
+    }
+    groups
+        .into_iter()
+        .filter(|(_, mappings)| {
+            mappings.iter().any(|m| m.ip != mappings[0].ip)
+                && mappings.iter().any(|m| m.id != system_id)
+        })
+        .map(|((hostname, _), mappings)| Conflict { hostname, mappings })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_conflicts() {
+        let sources = vec![
+            HostsSource::new("system", "system", "127.0.0.1 localhost\n::1 localhost\n10.0.0.9 db.dev"),
+            HostsSource::new("a", "staging", "10.0.0.1 api.dev web.dev\n10.0.0.2 db.dev"),
+            HostsSource::new("b", "dev", "# 10.0.0.3 web.dev\n10.0.0.1 web.dev\n127.0.0.1 API.dev"),
+        ];
+        let conflicts = find_conflicts(&sources, "system");
+        let hostnames = conflicts
+            .iter()
+            .map(|c| c.hostname.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(hostnames, vec!["api.dev", "db.dev"]);
+        assert_eq!(
+            conflicts[0].mappings[1],
+            Mapping {
+                id: "b".to_owned(),
+                title: "dev".to_owned(),
+                line_number: 3,
+                ip: "127.0.0.1".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_ignore_system_only_conflicts() {
+        let sources = vec![
+            HostsSource::new("system", "system", "127.0.0.1 a.dev\n10.0.0.1 a.dev"),
+            HostsSource::new("a", "staging", "10.0.0.2 b.dev"),
+        ];
+        assert!(find_conflicts(&sources, "system").is_empty());
+    }
+}
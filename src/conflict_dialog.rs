@@ -0,0 +1,72 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Widget},
+};
+
+use crate::conflict::Conflict;
+
+#[derive(Debug, Default)]
+pub struct ConflictDialog {
+    lines: Vec<String>,
+    scroll: u16,
+}
+
+impl ConflictDialog {
+    pub fn new() -> Self {
+        ConflictDialog::default()
+    }
+
+    pub fn set_conflicts(&mut self, conflicts: &[Conflict]) {
+        self.lines = conflicts.iter().flat_map(|c| c.describe()).collect();
+        self.scroll = 0;
+    }
+
+    /// 返回 Some(true) 表示继续写入，Some(false) 表示取消
+    pub fn handle_event(&mut self, event: KeyEvent) -> Option<bool> {
+        match event.code {
+            KeyCode::Enter | KeyCode::Char('y') | KeyCode::Char('Y') => Some(true),
+            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => Some(false),
+            KeyCode::Up => {
+                self.scroll = self.scroll.saturating_sub(1);
+                None
+            }
+            KeyCode::Down => {
+                if (self.scroll as usize) + 1 < self.lines.len() {
+                    self.scroll += 1;
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    pub fn draw(&mut self, area: Rect, buf: &mut Buffer) {
+        let strong_style = Style::default()
+            .add_modifier(Modifier::BOLD)
+            .fg(Color::LightGreen);
+        let footer = Line::from(vec![
+            Span::styled(" Enter/y", strong_style),
+            Span::raw(" 继续写入 "),
+            Span::styled("Esc/n", strong_style),
+            Span::raw(" 取消 "),
+        ]);
+        let block = Block::bordered()
+            .style(Style::new().white().on_black())
+            .border_style(Style::new().yellow())
+            .title("检测到主机名冲突")
+            .title_bottom(footer);
+        let lines = self
+            .lines
+            .iter()
+            .map(|line| Line::from(line.as_str()))
+            .collect::<Vec<_>>();
+        Paragraph::new(lines)
+            .block(block)
+            .scroll((self.scroll, 0))
+            .render(area, buf);
+    }
+}
@@ -1,41 +1,135 @@
+use chrono::{DateTime, Local};
 use log::error;
 use ratatui::{
     style::{Color, Modifier, Style},
-    text::Line,
+    text::{Line, Span},
     widgets::ListItem,
 };
-use serde::Serialize;
-use serde_json::{Number, Value};
-use std::{env, fs, path::PathBuf, vec::Vec};
-
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use std::{collections::HashSet, fs, path::PathBuf, vec::Vec};
+
+use crate::atomic_write::write_atomic;
+use crate::paths::resolve_data_dir;
+use crate::target::SYSTEM_TARGET;
+use crate::text_format::{normalize_line_endings, TextFormat};
 use crate::util::find_mut_config_by_id;
 use crate::util::Result;
 
-const SWITCH_HOSTS_RS_DIR: &str = ".SwitchHostsRs";
+/// 当前配置文件的版本，0 为早期的纯数组格式
+pub const CONFIG_VERSION: u64 = 1;
 
 #[derive(Clone, Debug, Default, Serialize, PartialEq)]
 pub enum ConfigItemType {
     System,
     #[default]
     User,
+    Remote,
+    Group,
 }
 
 impl From<i64> for ConfigItemType {
     fn from(value: i64) -> Self {
-        if value >= 1 {
-            ConfigItemType::User
-        } else {
-            ConfigItemType::System
+        match value {
+            2 => ConfigItemType::Remote,
+            3 => ConfigItemType::Group,
+            v if v >= 1 => ConfigItemType::User,
+            _ => ConfigItemType::System,
+        }
+    }
+}
+
+impl From<&Value> for ConfigItemType {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::String(s) if s == "System" => ConfigItemType::System,
+            Value::String(s) if s == "Remote" => ConfigItemType::Remote,
+            Value::String(s) if s == "Group" => ConfigItemType::Group,
+            Value::Number(n) => n.as_i64().unwrap_or(1).into(),
+            _ => ConfigItemType::User,
+        }
+    }
+}
+
+/// 兼容字符串和早期数字两种写法
+impl<'de> Deserialize<'de> for ConfigItemType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        Ok((&value).into())
+    }
+}
+
+/// 远程 hosts 的地址及最近一次拉取的状态
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RemoteSource {
+    pub url: String,
+    /// 自动刷新间隔（秒），0 表示只手动刷新
+    pub refresh_interval: u64,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// 最近一次拉取成功的时间（Unix 时间戳，秒）
+    pub last_updated: Option<i64>,
+    pub last_error: Option<String>,
+}
+
+impl RemoteSource {
+    /// 列表中展示的拉取状态
+    pub fn status(&self) -> String {
+        let updated = self
+            .last_updated
+            .and_then(|secs| DateTime::from_timestamp(secs, 0))
+            .map(|time| time.with_timezone(&Local).format("%m-%d %H:%M").to_string());
+        match (&self.last_error, updated) {
+            (Some(_), Some(updated)) => format!("[更新失败，上次更新于 {}]", updated),
+            (Some(_), None) => String::from("[更新失败]"),
+            (None, Some(updated)) => format!("[更新于 {}]", updated),
+            (None, None) => String::from("[未更新]"),
         }
     }
 }
 
-#[derive(Clone, Debug, Default, Serialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ConfigItem {
     id: String,
     on: bool,
     title: String,
     item_type: ConfigItemType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remote: Option<RemoteSource>,
+    /// 分组包含的 hosts id，按合并的顺序排列
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    members: Vec<String>,
+    /// 互斥集合的名称，同一集合中同时只能启用一个
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exclusive_set: Option<String>,
+    /// 写入的目标名称，为空时只写入系统 hosts
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    targets: Vec<String>,
+    /// 不认识的字段，写回时原样保留
+    #[serde(flatten)]
+    extra: Map<String, Value>,
+}
+
+/// config.json 的内容
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConfigDocument {
+    pub version: u64,
+    #[serde(default)]
+    pub items: Vec<ConfigItem>,
+    #[serde(flatten)]
+    extra: Map<String, Value>,
+}
+
+impl Default for ConfigDocument {
+    fn default() -> Self {
+        ConfigDocument {
+            version: CONFIG_VERSION,
+            items: vec![],
+            extra: Map::new(),
+        }
+    }
 }
 
 impl ConfigItem {
@@ -45,6 +139,39 @@ impl ConfigItem {
             on,
             title,
             item_type,
+            remote: None,
+            members: vec![],
+            exclusive_set: None,
+            targets: vec![],
+            extra: Map::new(),
+        }
+    }
+
+    pub fn new_remote(id: String, title: String, remote: RemoteSource) -> Self {
+        ConfigItem {
+            id,
+            on: false,
+            title,
+            item_type: ConfigItemType::Remote,
+            remote: Some(remote),
+            members: vec![],
+            exclusive_set: None,
+            targets: vec![],
+            extra: Map::new(),
+        }
+    }
+
+    pub fn new_group(id: String, title: String, members: Vec<String>) -> Self {
+        ConfigItem {
+            id,
+            on: false,
+            title,
+            item_type: ConfigItemType::Group,
+            remote: None,
+            members,
+            exclusive_set: None,
+            targets: vec![],
+            extra: Map::new(),
         }
     }
 
@@ -71,11 +198,66 @@ impl ConfigItem {
     pub fn item_type(&self) -> &ConfigItemType {
         &self.item_type
     }
+
+    pub fn remote(&self) -> Option<&RemoteSource> {
+        self.remote.as_ref()
+    }
+
+    pub fn remote_mut(&mut self) -> Option<&mut RemoteSource> {
+        self.remote.as_mut()
+    }
+
+    pub fn is_group(&self) -> bool {
+        self.item_type == ConfigItemType::Group
+    }
+
+    pub fn members(&self) -> &Vec<String> {
+        &self.members
+    }
+
+    pub fn set_members(&mut self, members: Vec<String>) {
+        self.members = members;
+    }
+
+    pub fn exclusive_set(&self) -> Option<&String> {
+        self.exclusive_set.as_ref()
+    }
+
+    pub fn set_exclusive_set(&mut self, exclusive_set: Option<String>) {
+        self.exclusive_set = exclusive_set;
+    }
+
+    /// 写入的目标名称，没有指定时为系统 hosts
+    pub fn targets(&self) -> Vec<String> {
+        if self.targets.is_empty() {
+            vec![SYSTEM_TARGET.to_owned()]
+        } else {
+            self.targets.clone()
+        }
+    }
+
+    pub fn applies_to(&self, target: &str) -> bool {
+        self.targets().iter().any(|name| name == target)
+    }
+
+    /// 只有系统 hosts 时不保存，与没有指定目标相同
+    pub fn set_targets(&mut self, targets: Vec<String>) {
+        self.targets = if targets == [SYSTEM_TARGET] {
+            vec![]
+        } else {
+            targets
+        };
+    }
+
+    /// system、远程 hosts 和分组的内容不能在编辑器中修改
+    pub fn is_read_only(&self) -> bool {
+        self.item_type != ConfigItemType::User
+    }
 }
 
 impl From<&ConfigItem> for ListItem<'_> {
     fn from(value: &ConfigItem) -> Self {
-        let line = if value.on {
+        let mut line = if value.on {
             Line::styled(
                 format!("✓ {}", value.title),
                 Style::new()
@@ -88,16 +270,45 @@ impl From<&ConfigItem> for ListItem<'_> {
                 Style::new().fg(Color::White).add_modifier(Modifier::BOLD),
             )
         };
+        if let Some(remote) = &value.remote {
+            line.push_span(Span::styled(
+                format!(" {}", remote.status()),
+                if remote.last_error.is_some() {
+                    Style::new().fg(Color::LightRed)
+                } else {
+                    Style::new().fg(Color::Gray)
+                },
+            ));
+        }
+        if let Some(exclusive_set) = &value.exclusive_set {
+            line.push_span(Span::styled(
+                format!(" [互斥: {}]", exclusive_set),
+                Style::new().fg(Color::LightCyan),
+            ));
+        }
+        if value.is_group() {
+            line.push_span(Span::styled(
+                format!(" [分组 {} 项]", value.members.len()),
+                Style::new().fg(Color::Gray),
+            ));
+        }
+        if !value.targets.is_empty() {
+            line.push_span(Span::styled(" [目标:", Style::new().fg(Color::Gray)));
+            // 只列出目标名称，不标记写入状态：目标无法读取时会被跳过，启用不代表已写入每个目标
+            for target in &value.targets {
+                line.push_span(Span::styled(
+                    format!(" {}", target),
+                    Style::new().fg(Color::Gray),
+                ));
+            }
+            line.push_span(Span::styled("]", Style::new().fg(Color::Gray)));
+        }
         ListItem::new(line)
     }
 }
 
-pub fn get_home_dir() -> Option<PathBuf> {
-    env::home_dir()
-}
-
 pub fn get_switch_hosts_rs_dir() -> Option<PathBuf> {
-    get_home_dir().map(|buf| buf.join(SWITCH_HOSTS_RS_DIR))
+    resolve_data_dir()
 }
 
 pub fn get_config_path() -> Option<PathBuf> {
@@ -111,7 +322,7 @@ pub fn get_data_dir() -> Option<PathBuf> {
 pub fn check_switch_host_rs_dir_exist() -> Result<()> {
     let dir = get_switch_hosts_rs_dir().unwrap();
     if !fs::exists(&dir)? {
-        fs::create_dir(&dir).or_else(|e| {
+        fs::create_dir_all(&dir).or_else(|e| {
             error!("{e}");
             Err(e)
         });
@@ -130,6 +341,7 @@ pub fn check_data_dir_exist() -> Result<()> {
     Ok(())
 }
 
+/// 读取 hosts 内容，换行符统一为 `\n`
 pub fn read_item_data(id: &String) -> Result<String> {
     check_switch_host_rs_dir_exist()?;
     check_data_dir_exist()?;
@@ -138,16 +350,78 @@ pub fn read_item_data(id: &String) -> Result<String> {
     if !fs::exists(file_name)? {
         Ok("".to_owned())
     } else {
-        Ok(fs::read_to_string(file_name)?)
+        let buf = fs::read(file_name)?;
+        Ok(normalize_line_endings(
+            &TextFormat::detect(&buf).decode(&buf),
+        ))
+    }
+}
+
+/// 按顺序合并多份 hosts 内容，重复的行只保留第一次出现的
+pub fn merge_hosts_content(contents: &[String]) -> String {
+    let mut seen = HashSet::new();
+    let mut merged = vec![];
+    for content in contents {
+        for line in content.lines() {
+            let key = line.split_whitespace().collect::<Vec<_>>().join(" ");
+            if key.is_empty() || seen.insert(key) {
+                merged.push(line);
+            }
+        }
     }
+    merged.join("\n")
 }
 
+/// hosts 实际生效的内容，分组为成员内容的合并
+pub fn read_hosts_content(id: &String) -> Result<String> {
+    let config = read_config()?;
+    match config.iter().find(|item| item.id() == id) {
+        Some(item) if item.is_group() => {
+            let mut contents = vec![];
+            for member in item.members() {
+                if config
+                    .iter()
+                    .any(|item| item.id() == member && !item.is_group())
+                {
+                    contents.push(read_item_data(member)?);
+                }
+            }
+            Ok(merge_hosts_content(&contents))
+        }
+        _ => read_item_data(id),
+    }
+}
+
+/// hosts 内容最后修改的时间，分组取成员中最晚的，内容文件不存在时返回 None
+pub fn read_hosts_modified(id: &String) -> Result<Option<DateTime<Local>>> {
+    let config = read_config()?;
+    let ids = match config.iter().find(|item| item.id() == id) {
+        Some(item) if item.is_group() => item.members().to_vec(),
+        _ => vec![id.clone()],
+    };
+    let data_dir = get_data_dir().unwrap();
+    Ok(ids
+        .iter()
+        .filter_map(|id| fs::metadata(data_dir.join(format!("{}.txt", id))).ok())
+        .filter_map(|metadata| metadata.modified().ok())
+        .map(DateTime::<Local>::from)
+        .max())
+}
+
+/// 写入 hosts 内容，沿用原文件的换行符、BOM 与编码，新文件按内容中较多的换行符写入
 pub fn write_item_data(id: &String, content: String) -> Result<()> {
     check_switch_host_rs_dir_exist()?;
     check_data_dir_exist()?;
     let data_dir = get_data_dir().unwrap();
     let file_name = &data_dir.join(format!("{}.txt", id));
-    fs::write(file_name, content)?;
+    let format = match fs::read(file_name) {
+        Ok(buf) => TextFormat::detect(&buf),
+        Err(_) => TextFormat::detect(content.as_bytes()),
+    };
+    write_atomic(
+        file_name,
+        format.encode(&format.line_ending.apply(&content)),
+    )?;
     Ok(())
 }
 
@@ -171,45 +445,75 @@ pub fn add_item(id: String, title: String, content: String) -> Result<()> {
     check_data_dir_exist()?;
     let data_dir = get_data_dir().unwrap();
     let file_name = &data_dir.join(format!("{}.txt", id.clone()));
-    match fs::write(file_name, content) {
-        Ok(_) => add_config_item(id, title),
-        Err(err) => Err(err.into()),
+    write_atomic(file_name, content)?;
+    add_config_item(id, title)
+}
+
+/// 把旧版本的配置升级到当前版本
+fn migrate_config(value: Value) -> Result<Value> {
+    let mut value = match value {
+        Value::Null => serde_json::json!({ "version": 0, "items": [] }),
+        Value::Array(items) => serde_json::json!({ "version": 0, "items": items }),
+        Value::Object(_) => value,
+        _ => return Err(color_eyre::eyre::eyre!("corrupt config: 不是对象或数组")),
+    };
+    let version = value["version"].as_u64().unwrap_or(0);
+    if version > CONFIG_VERSION {
+        return Err(color_eyre::eyre::eyre!(
+            "corrupt config: 不支持的版本 {}",
+            version
+        ));
     }
+    if version < 1 {
+        // 0 -> 1: 纯数组改为带版本号的对象
+        value["version"] = Value::from(1);
+    }
+    Ok(value)
 }
 
-pub fn read_config() -> Result<Vec<ConfigItem>> {
+/// 解析 config.json 的内容，空内容视为空配置，无法解析时返回 `corrupt config` 错误
+pub fn parse_config(content: &str) -> Result<ConfigDocument> {
+    if content.trim().is_empty() {
+        return Ok(ConfigDocument::default());
+    }
+    let value = serde_json::from_str::<Value>(content)
+        .map_err(|e| color_eyre::eyre::eyre!("corrupt config: {}", e))?;
+    let mut document: ConfigDocument = serde_json::from_value(migrate_config(value)?)
+        .map_err(|e| color_eyre::eyre::eyre!("corrupt config: {}", e))?;
+    document.items.retain(|item| {
+        if item.id.is_empty() {
+            error!("skip config item without id: {:?}", item);
+        }
+        !item.id.is_empty()
+    });
+    for item in document.items.iter_mut() {
+        if item.title.is_empty() {
+            item.title = item.id.clone();
+        }
+    }
+    Ok(document)
+}
+
+pub fn read_config_document() -> Result<ConfigDocument> {
     check_switch_host_rs_dir_exist()?;
     let path = get_config_path().unwrap();
-    let empty = Vec::new();
     if !fs::exists(&path)? {
         fs::write(&path, "")?;
-        Ok(empty)
-    } else {
-        let content = String::from_utf8(fs::read(&path)?).unwrap();
-        serde_json::from_str(&content).map_or(Ok(empty.clone()), |value: Value| match value {
-            Value::Array(val) => Ok(val
-                .iter()
-                .map(|item| ConfigItem {
-                    id: item["id"].as_str().unwrap().to_owned(),
-                    on: item["on"].as_bool().unwrap_or(false),
-                    title: item["title"].as_str().unwrap().to_owned(),
-                    item_type: item["item_type"]
-                        .as_number()
-                        .unwrap_or(&Number::from(1))
-                        .as_i64()
-                        .unwrap()
-                        .into(),
-                })
-                .collect()),
-            _ => Ok(empty),
-        })
+        return Ok(ConfigDocument::default());
     }
+    let content = String::from_utf8(fs::read(&path)?)
+        .map_err(|e| color_eyre::eyre::eyre!("corrupt config: {}", e))?;
+    parse_config(&content)
+}
+
+pub fn read_config() -> Result<Vec<ConfigItem>> {
+    Ok(read_config_document()?.items)
 }
 
 pub fn write_config(content: impl Into<String> + AsRef<[u8]>) -> Result<()> {
     check_switch_host_rs_dir_exist()?;
     let path = get_config_path().unwrap();
-    fs::write(&path, &content)?;
+    write_atomic(&path, &content)?;
     Ok(())
 }
 
@@ -222,8 +526,12 @@ pub fn delete_config_item(id: &String) -> Result<()> {
     Ok(())
 }
 
-pub fn deserialize_and_write_config(config: &Vec<ConfigItem>) -> Result<()> {
-    let json = serde_json::to_string_pretty(&config)?;
+/// 替换配置中的 hosts 列表，文件中的其他字段保持不变。配置文件损坏时不会覆盖
+pub fn deserialize_and_write_config(config: &[ConfigItem]) -> Result<()> {
+    let mut document = read_config_document()?;
+    document.version = CONFIG_VERSION;
+    document.items = config.to_vec();
+    let json = serde_json::to_string_pretty(&document)?;
     write_config(json)?;
     Ok(())
 }
@@ -237,25 +545,29 @@ pub fn add_config_item(id: String, title: String) -> Result<()> {
             deserialize_and_write_config(&config)?;
         }
         _ => {
-            config.push(ConfigItem {
-                id,
-                title,
-                on: false,
-                item_type: ConfigItemType::User,
-            });
+            config.push(ConfigItem::new(id, false, title, ConfigItemType::User));
             deserialize_and_write_config(&config)?;
         }
     }
     Ok(())
 }
 
+/// 新增或整体替换一条配置
+pub fn save_config_item(item: &ConfigItem) -> Result<()> {
+    let mut config = read_config()?;
+    match find_mut_config_by_id(&mut config, item.id()) {
+        Some(target) => *target = item.clone(),
+        None => config.push(item.clone()),
+    }
+    deserialize_and_write_config(&config)
+}
+
 pub fn update_config_item(id: String, new_config: &ConfigItem) -> Result<()> {
     let mut config = read_config()?;
     if let Some(target) = find_mut_config_by_id(&mut config, &id) {
         target.on = new_config.is_on();
         target.title = new_config.title().to_owned();
-        let new_config_json = serde_json::to_string_pretty(&config)?;
-        write_config(new_config_json)?;
+        deserialize_and_write_config(&config)?;
     }
     Ok(())
 }
@@ -266,21 +578,90 @@ mod tests {
     use color_eyre::eyre::Ok;
 
     use super::*;
+    use crate::paths::with_temp_data_dir;
 
     #[test]
     fn test_read_writer_config() -> Result<()> {
-        write_config("")?;
-        let data = r#"
-            [
-                { "id": "a", "on": true, "title": "A" },
-                { "id": "b", "on": false, "title": "B" }
-            ]
-        "#;
-        write_config(data)?;
-        let config = read_config()?;
-        assert_eq!(config.len(), 2);
-        assert_eq!(config[0].id, "a".to_owned());
-        assert_eq!(config[1].id, "b".to_owned());
+        with_temp_data_dir(|dir| {
+            write_config("")?;
+            let data = r#"
+                [
+                    { "id": "a", "on": true, "title": "A" },
+                    { "id": "b", "on": false, "title": "B" }
+                ]
+            "#;
+            write_config(data)?;
+            assert!(dir.join("config.json").exists());
+            let config = read_config()?;
+            assert_eq!(config.len(), 2);
+            assert_eq!(config[0].id, "a".to_owned());
+            assert_eq!(config[1].id, "b".to_owned());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_item_data_keeps_format() -> Result<()> {
+        with_temp_data_dir(|dir| {
+            let id = "a".to_owned();
+            write_item_data(&id, "10.0.0.1 a.test\r\n".to_owned())?;
+            let path = dir.join("data").join("a.txt");
+            fs::write(&path, "\u{feff}10.0.0.1 a.test\r\n# old\r\n")?;
+            assert_eq!(read_item_data(&id)?, "10.0.0.1 a.test\n# old\n");
+            write_item_data(&id, "10.0.0.2 b.test\n# new\n".to_owned())?;
+            assert_eq!(
+                fs::read_to_string(&path)?,
+                "\u{feff}10.0.0.2 b.test\r\n# new\r\n"
+            );
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_parse_config() -> Result<()> {
+        let document = parse_config(
+            r#"[{ "id": "a", "on": true, "item_type": 1, "color": "red" }, { "title": "no id" }]"#,
+        )?;
+        assert_eq!(document.version, CONFIG_VERSION);
+        assert_eq!(document.items.len(), 1);
+        assert_eq!(document.items[0].title, "a");
+        assert_eq!(document.items[0].item_type, ConfigItemType::User);
+        let json = serde_json::to_value(&document)?;
+        assert_eq!(json["items"][0]["color"], "red");
+
+        let document = parse_config(r#"{ "version": 1, "items": [], "theme": "dark" }"#)?;
+        assert_eq!(serde_json::to_value(&document)?["theme"], "dark");
+
+        assert!(parse_config("[{ \"id\": ").is_err());
+        assert!(parse_config(r#"{ "version": 99, "items": [] }"#).is_err());
+        assert!(parse_config("").unwrap().items.is_empty());
         Ok(())
     }
+
+    #[test]
+    fn test_item_targets() -> Result<()> {
+        let mut item = ConfigItem::new("a".to_owned(), true, "A".to_owned(), ConfigItemType::User);
+        assert_eq!(item.targets(), vec![SYSTEM_TARGET]);
+        assert!(serde_json::to_value(&item)?.get("targets").is_none());
+        item.set_targets(vec!["system".to_owned(), "web".to_owned()]);
+        assert!(item.applies_to("web"));
+        assert_eq!(serde_json::to_value(&item)?["targets"][1], "web");
+        item.set_targets(vec!["system".to_owned()]);
+        assert!(serde_json::to_value(&item)?.get("targets").is_none());
+        item.set_targets(vec!["web".to_owned()]);
+        assert!(!item.applies_to(SYSTEM_TARGET));
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_hosts_content() {
+        let contents = vec![
+            "# api\n10.0.0.1 api.test\n\n10.0.0.2 web.test".to_owned(),
+            "10.0.0.2   web.test\n10.0.0.3 db.test".to_owned(),
+        ];
+        assert_eq!(
+            merge_hosts_content(&contents),
+            "# api\n10.0.0.1 api.test\n\n10.0.0.2 web.test\n10.0.0.3 db.test"
+        );
+    }
 }
@@ -0,0 +1,31 @@
+use similar::TextDiff;
+
+const CONTEXT_RADIUS: usize = 3;
+
+/// 生成 unified diff，内容相同时返回空字符串
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    if old == new {
+        return String::new();
+    }
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(CONTEXT_RADIUS)
+        .header(old_name, new_name)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff() {
+        let old = "127.0.0.1 localhost\n";
+        let new = "127.0.0.1 localhost\n10.0.0.1 api.dev\n";
+        assert_eq!(unified_diff(old, old, "a", "b"), "");
+        assert_eq!(
+            unified_diff(old, new, "a", "b"),
+            "--- a\n+++ b\n@@ -1 +1,2 @@\n 127.0.0.1 localhost\n+10.0.0.1 api.dev\n"
+        );
+    }
+}
@@ -1,23 +1,36 @@
+use crate::hosts::{managed_block_rows, BlockMarker};
 use crate::observer::Observer;
+use crate::target::Target;
 use crate::util::Result;
+use crate::validator::{validate_hosts, Diagnostic};
 use crate::{
-    data::{read_item_data, write_item_data},
+    data::{read_hosts_content, write_item_data},
     hosts::read_sys_hosts,
 };
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use log::debug;
 use ratatui::{
+    layout::{Constraint, Layout},
     prelude::{Buffer, Rect},
-    style::{Modifier, Style, Stylize},
+    style::{Color, Modifier, Style, Stylize},
+    text::Line,
     widgets::{block::Block, Widget},
 };
 use tui_textarea::{CursorMove, TextArea};
 
+const GUTTER_WIDTH: u16 = 2;
+
 pub struct Editor<'a> {
     name: String,
     textarea: TextArea<'a>,
     id: String,
     activated: bool,
+    read_only: bool,
+    block_style: Style,
+    diagnostics: Vec<Diagnostic>,
+    scroll_top: usize,
+    /// 编辑系统 hosts 时受管理区块的标记，为 None 时系统 hosts 只读
+    system_markers: Option<Vec<BlockMarker>>,
 }
 
 impl Editor<'_> {
@@ -28,6 +41,11 @@ impl Editor<'_> {
             textarea,
             id: "".to_owned(),
             activated: false,
+            read_only: false,
+            block_style: Style::default(),
+            diagnostics: vec![],
+            scroll_top: 0,
+            system_markers: None,
         };
         editor.inactivate();
         editor
@@ -37,6 +55,47 @@ impl Editor<'_> {
         self.id = id;
     }
 
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    /// 编辑系统 hosts，只能修改受管理区块以外的内容，保存由调用方确认后写入
+    pub fn begin_system_edit(&mut self) -> Result<()> {
+        self.system_markers = Some(Target::system().block_markers()?);
+        self.activate();
+        Ok(())
+    }
+
+    pub fn end_system_edit(&mut self) {
+        self.system_markers = None;
+    }
+
+    pub fn is_editing_system(&self) -> bool {
+        self.system_markers.is_some()
+    }
+
+    /// 受管理区块所在的行，包括起止标记
+    fn locked_rows(&self) -> Option<(usize, usize)> {
+        managed_block_rows(&self.get_text(), self.system_markers.as_ref()?)
+    }
+
+    /// 按键是否会修改受管理区块：光标在区块内，或在区块前后删除换行符把其他行并入区块
+    fn touches_locked_rows(&self, event: KeyEvent) -> bool {
+        let Some((start, end)) = self.locked_rows() else {
+            return false;
+        };
+        let (row, col) = self.textarea.cursor();
+        let line_len = self.textarea.lines()[row].chars().count();
+        let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+        let deletes_backward = event.code == KeyCode::Backspace
+            || (ctrl && matches!(event.code, KeyCode::Char('h' | 'w')));
+        let deletes_forward = event.code == KeyCode::Delete
+            || (ctrl && matches!(event.code, KeyCode::Char('d' | 'k')));
+        (start..=end).contains(&row)
+            || (row == end + 1 && col == 0 && deletes_backward)
+            || (row + 1 == start && col == line_len && deletes_forward)
+    }
+
     pub fn get_text(&self) -> String {
         let lines = self.textarea.lines();
         let text = lines.join("\n");
@@ -52,9 +111,7 @@ impl Editor<'_> {
         self.activated = false;
         self.textarea.set_cursor_line_style(Style::default());
         self.textarea.set_cursor_style(Style::default());
-        let style = Style::new().white().on_dark_gray().bold();
-        let block = Block::bordered().style(style).title("Hosts Content");
-        self.textarea.set_block(block);
+        self.block_style = Style::new().white().on_dark_gray().bold();
     }
 
     pub fn activate(&mut self) {
@@ -63,9 +120,15 @@ impl Editor<'_> {
             .set_cursor_line_style(Style::default().add_modifier(Modifier::UNDERLINED));
         self.textarea
             .set_cursor_style(Style::default().add_modifier(Modifier::REVERSED));
-        let style = Style::new().white().on_black().bold();
-        let block = Block::bordered().style(style).title("Hosts Content");
-        self.textarea.set_block(block);
+        self.block_style = Style::new().white().on_black().bold();
+    }
+
+    pub fn validate(&mut self) {
+        self.diagnostics = validate_hosts(&self.get_text());
+    }
+
+    pub fn get_diagnostics(&self) -> &Vec<Diagnostic> {
+        &self.diagnostics
     }
 
     pub fn cursor_move_up(&mut self) {
@@ -84,26 +147,32 @@ impl Editor<'_> {
         &mut self,
         event: KeyEvent,
     ) -> Option<bool> {
-        let is_system = self.id == String::from("system");
+        let editing_system = self.is_editing_system();
+        let read_only = self.read_only || (self.id == "system" && !editing_system);
         match (event.modifiers, event.code) {
             (_, KeyCode::Esc) => {
                 self.inactivate();
-                self.save_item_content(self.get_text());
+                if !read_only && !editing_system {
+                    self.save_item_content(self.get_text());
+                }
                 Some(true)
             }
             (KeyModifiers::CONTROL, KeyCode::Char('s') | KeyCode::Char('S')) => {
-                if is_system {
+                if read_only {
                     return None
                 }
-                self.save_item_content(self.get_text());
+                if !editing_system {
+                    self.save_item_content(self.get_text());
+                }
 
                 Some(false)
             }
             (KeyModifiers::CONTROL, KeyCode::Char('z') | KeyCode::Char('Z')) => {
-                if is_system {
+                if read_only {
                     return None;
                 }
                 self.textarea.undo();
+                self.validate();
                 None
             }
             (KeyModifiers::ALT, KeyCode::Left) => {
@@ -115,7 +184,11 @@ impl Editor<'_> {
                 None
             }
             (KeyModifiers::SHIFT,  KeyCode::Char('d') | KeyCode::Char('D')) => {
+                if read_only || self.touches_locked_rows(event) {
+                    return None;
+                }
                 self.textarea.delete_line_by_head();
+                self.validate();
                 None
             }
             (KeyModifiers::SHIFT, KeyCode::Char('o') | KeyCode::Char('O')) => {
@@ -127,22 +200,78 @@ impl Editor<'_> {
                 None
             }
             other => {
-                if is_system
-                    && other.1 != KeyCode::Up
-                    && other.1 != KeyCode::Down
-                    && other.1 != KeyCode::Left
-                    && other.1 != KeyCode::Right
-                {
+                let moves_cursor = other.1 == KeyCode::Up
+                    || other.1 == KeyCode::Down
+                    || other.1 == KeyCode::Left
+                    || other.1 == KeyCode::Right;
+                if !moves_cursor && (read_only || self.touches_locked_rows(event)) {
                     return None;
                 }
-                self.textarea.input(event);
+                if self.textarea.input(event) {
+                    self.validate();
+                }
                 None
             }
         }
     }
 
+    fn cursor_line_message(&self) -> Option<Line<'static>> {
+        let row = self.textarea.cursor().0 + 1;
+        if let Some(diagnostic) = self.diagnostics.iter().find(|d| d.line_number == row) {
+            return Some(Line::from(format!(" 第 {} 行: {} ", row, diagnostic.message)).red());
+        }
+        if !self.diagnostics.is_empty() {
+            return Some(Line::from(format!(" {} 个问题 ", self.diagnostics.len())).red());
+        }
+        None
+    }
+
     pub fn draw(&mut self, area: Rect, buf: &mut Buffer) {
-        self.textarea.render(area, buf);
+        let title = if self.is_editing_system() {
+            "Hosts Content（编辑系统 hosts，受管理区块已锁定）"
+        } else {
+            "Hosts Content"
+        };
+        let mut block = Block::bordered().style(self.block_style).title(title);
+        if let Some(message) = self.cursor_line_message() {
+            block = block.title_bottom(message);
+        }
+        let inner = block.inner(area);
+        block.render(area, buf);
+        let [gutter, text_area] =
+            Layout::horizontal([Constraint::Length(GUTTER_WIDTH), Constraint::Fill(1)])
+                .areas(inner);
+        self.textarea.render(text_area, buf);
+        // 与 TextArea 内部的滚动逻辑保持一致，用于计算可见行
+        let height = text_area.height as usize;
+        let cursor_row = self.textarea.cursor().0;
+        if cursor_row < self.scroll_top {
+            self.scroll_top = cursor_row;
+        } else if height > 0 && self.scroll_top + height <= cursor_row {
+            self.scroll_top = cursor_row + 1 - height;
+        }
+        if let Some((start, end)) = self.locked_rows() {
+            let first = start.max(self.scroll_top);
+            let last = end.min(self.scroll_top + height.saturating_sub(1));
+            for row in (first..=last).map(|row| (row - self.scroll_top) as u16) {
+                buf.set_string(gutter.x, gutter.y + row, "│", Style::new().blue());
+                buf.set_style(
+                    Rect::new(text_area.x, text_area.y + row, text_area.width, 1),
+                    Style::new().bg(Color::Rgb(30, 30, 70)),
+                );
+            }
+        }
+        for diagnostic in &self.diagnostics {
+            let row = match (diagnostic.line_number - 1).checked_sub(self.scroll_top) {
+                Some(row) if row < height => row as u16,
+                _ => continue,
+            };
+            buf.set_string(gutter.x, gutter.y + row, "●", Style::new().red());
+            buf.set_style(
+                Rect::new(text_area.x, text_area.y + row, text_area.width, 1),
+                Style::new().bg(Color::Rgb(90, 20, 20)),
+            );
+        }
     }
 }
 
@@ -154,13 +283,15 @@ impl Observer for Editor<'_> {
             if id == String::from("system") {
                 read_sys_hosts()
             } else {
-                read_item_data(&id)
+                read_hosts_content(&id)
             }
         };
         res.and_then(|content| {
             // 过滤掉\r\n，windows上会导致editor布局错乱
             let textarea: TextArea<'_> = TextArea::from(content.replace("\r\n", "\n").split("\n"));
             self.textarea = textarea;
+            self.scroll_top = 0;
+            self.validate();
             if self.activated {
                 self.activate();
             } else {
@@ -0,0 +1,50 @@
+use crate::single_line_textarea::{
+    create_new_single_line_textarea, SingleLineTextarea, SinglelineTextareaType,
+};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{buffer::Buffer, layout::Rect};
+
+#[derive(Debug, Default)]
+pub struct ExclusiveSetInput<'a> {
+    textarea: SingleLineTextarea<'a>,
+}
+
+impl<'a> ExclusiveSetInput<'a> {
+    pub fn new() -> Self {
+        ExclusiveSetInput {
+            textarea: create_new_single_line_textarea(
+                "留空表示移出互斥集合",
+                "互斥集合",
+                SinglelineTextareaType::Text,
+            ),
+        }
+    }
+
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        self.textarea.set_text(text);
+        self.textarea.move_cursor_to_end();
+    }
+
+    /// 返回 (是否结束输入, 输入的集合名称)，确认时名称可能为空
+    pub fn handle_event(&mut self, event: KeyEvent) -> (bool, Option<String>) {
+        match event.code {
+            KeyCode::Esc => {
+                self.textarea.set_text("");
+                (true, None)
+            }
+            KeyCode::Enter => {
+                let text = self.textarea.get_text().trim().to_owned();
+                self.textarea.set_text("");
+                (true, Some(text))
+            }
+            _ => {
+                self.textarea.input(event);
+                (false, None)
+            }
+        }
+    }
+
+    pub fn draw(&mut self, area: Rect, buf: &mut Buffer) {
+        self.textarea.draw(area, buf);
+    }
+}
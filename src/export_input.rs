@@ -0,0 +1,68 @@
+use crate::single_line_textarea::{
+    create_new_single_line_textarea, SingleLineTextarea, SinglelineTextareaType,
+};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{buffer::Buffer, layout::Rect};
+
+const BLOCK_TITLE: &str = "导出受管理区块的内容（Tab 切换为完整系统 hosts）";
+
+const FULL_TITLE: &str = "导出完整系统 hosts（Tab 切换为受管理区块的内容）";
+
+#[derive(Debug, Default)]
+pub struct ExportInput<'a> {
+    textarea: SingleLineTextarea<'a>,
+    full: bool,
+}
+
+impl<'a> ExportInput<'a> {
+    pub fn new() -> Self {
+        ExportInput {
+            textarea: create_new_single_line_textarea(
+                "导出到的文件路径",
+                BLOCK_TITLE,
+                SinglelineTextareaType::Text,
+            ),
+            full: false,
+        }
+    }
+
+    /// 是否导出写入后的完整系统 hosts
+    pub fn is_full(&self) -> bool {
+        self.full
+    }
+
+    /// 返回 (是否结束输入, 输入的路径)
+    pub fn handle_event(&mut self, event: KeyEvent) -> (bool, Option<String>) {
+        match event.code {
+            KeyCode::Esc => {
+                self.textarea.set_text("");
+                (true, None)
+            }
+            KeyCode::Tab => {
+                self.full = !self.full;
+                self.textarea
+                    .set_title(if self.full { FULL_TITLE } else { BLOCK_TITLE });
+                (false, None)
+            }
+            KeyCode::Enter => {
+                let text = self.textarea.get_text().trim().to_owned();
+                if text.is_empty() {
+                    self.textarea.set_error("请输入文件路径");
+                    (false, None)
+                } else {
+                    self.textarea.set_text("");
+                    (true, Some(text))
+                }
+            }
+            _ => {
+                self.textarea.set_error("");
+                self.textarea.input(event);
+                (false, None)
+            }
+        }
+    }
+
+    pub fn draw(&mut self, area: Rect, buf: &mut Buffer) {
+        self.textarea.draw(area, buf);
+    }
+}
@@ -1,19 +1,75 @@
-use std::{fmt::Display, fs, process::Command};
-
-#[cfg(not(target_os = "windows"))]
-use std::os::unix::fs::PermissionsExt;
+use std::{fs, io::ErrorKind};
 
 #[cfg(target_os = "windows")]
 use std::env;
 
+use crate::atomic_write::write_atomic;
+use crate::backup::create_backup;
+use crate::hosts_parser::HostsDocument;
+use crate::paths::resolve_hosts_file;
+use crate::settings::read_settings;
+use crate::target::Target;
+use crate::text_format::{normalize_line_endings, Encoding, LineEnding, TextFormat};
 use crate::util::Result;
+use log::error;
+use similar::{capture_diff_slices, Algorithm, DiffTag};
+
+/// 默认的受管理区块标记名称
+const DEFAULT_MARKER: &str = "SWITCHHOSTS_RS_CONTENT";
+
+/// 写入后必须仍然映射到 localhost 的地址
+const ESSENTIAL_IPS: [&str; 2] = ["127.0.0.1", "::1"];
+
+/// 受管理区块的起止标记 `# --- <名称>_START ---` 和 `# --- <名称>_END ---`，
+/// 不同的安装或其他 hosts 管理工具使用不同的名称就不会覆盖彼此的区块
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockMarker {
+    start: String,
+    end: String,
+}
 
-const CONTENT_START: &str = "# --- SWITCHHOSTS_RS_CONTENT_START ---";
+impl BlockMarker {
+    /// 名称只能包含字母、数字、`_` 和 `-`，不符合时返回 `invalid marker`。
+    /// 不能退回到默认名称，否则不同的安装会共用同一个区块
+    pub fn new(name: &str) -> Result<Self> {
+        let name = name.trim();
+        if !is_valid_marker_name(name) {
+            error!("invalid marker: {name}");
+            return Err(color_eyre::eyre::Error::msg("invalid marker"));
+        }
+        Ok(BlockMarker {
+            start: format!("# --- {name}_START ---"),
+            end: format!("# --- {name}_END ---"),
+        })
+    }
 
-const CONTENT_END: &str = "# --- SWITCHHOSTS_RS_CONTENT_END ---";
+    /// 区块在内容中的位置，返回 (起始标记的开头, 结束标记的开头)
+    fn find(&self, content: &[u8]) -> Option<(usize, usize)> {
+        let start = find_bytes(content, self.start.as_bytes())?;
+        let end = find_bytes(content, self.end.as_bytes())?;
+        Some((start, end)).filter(|(start, end)| start + self.start.len() <= *end)
+    }
+}
+
+pub fn is_valid_marker_name(name: &str) -> bool {
+    let name = name.trim();
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+impl Default for BlockMarker {
+    fn default() -> Self {
+        BlockMarker {
+            start: format!("# --- {DEFAULT_MARKER}_START ---"),
+            end: format!("# --- {DEFAULT_MARKER}_END ---"),
+        }
+    }
+}
 
 #[cfg(target_os = "windows")]
-fn get_sys_hosts_path() -> String {
+fn default_sys_hosts_path() -> String {
     let windir = env::var("windir").and_then(|mut dir| {
         dir.push_str(r"\system32\drivers\etc\hosts");
         Ok(dir)
@@ -24,104 +80,730 @@ fn get_sys_hosts_path() -> String {
 }
 
 #[cfg(not(target_os = "windows"))]
-fn get_sys_hosts_path() -> String {
+fn default_sys_hosts_path() -> String {
     String::from("/etc/hosts")
 }
 
+/// 命令行参数、环境变量或设置中指定了 hosts 路径时使用指定的路径，如容器中挂载的 hosts
+pub fn get_sys_hosts_path() -> String {
+    resolve_hosts_file(read_settings().hosts_file.as_deref())
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(default_sys_hosts_path)
+}
+
 pub fn write_sys_hosts(appended: impl Into<String> + AsRef<[u8]>) -> Result<()> {
-    let hosts_path = get_sys_hosts_path();
-    let hosts_content = generate_sys_hosts_content(appended.into());
-    fs::write(&hosts_path, &hosts_content)?;
-    Ok(())
+    let hosts_content = generate_sys_hosts_content(appended.into())?;
+    replace_sys_hosts(hosts_content)
 }
 
-#[cfg(target_os = "windows")]
-pub fn check_password_correct<'a>(password: String, callback: impl Fn() -> ()) -> Result<()>  {
-    Ok(())
+/// 用完整内容覆盖系统 hosts
+pub fn replace_sys_hosts(content: Vec<u8>) -> Result<()> {
+    replace_hosts_file(&get_sys_hosts_path(), content)
 }
 
-#[cfg(not(target_os = "windows"))]
-pub fn check_password_correct<'a>(password: String, callback: impl Fn() -> ()) -> Result<()> {
-    let sys_hosts_path = get_sys_hosts_path();
-    let metadata = fs::metadata(&sys_hosts_path)?;
-    let old_mode = metadata.permissions().mode();
-    let mask = 0o000777;
-    let old_permission_mode = old_mode & mask;
-    set_sudo_permissions(&password)?;
-    callback();
-    resume_permissions(&password, format!("{:o}", old_permission_mode).as_str())?;
+/// 用完整内容覆盖指定的 hosts 文件，系统 hosts 写入前会先备份
+pub fn replace_hosts_file(path: &str, content: Vec<u8>) -> Result<()> {
+    check_before_write(path, &content)?;
+    backup_if_sys_hosts(path);
+    write_atomic(path, &content)?;
     Ok(())
 }
 
-pub fn set_sudo_permissions<'a>(password: &String) -> Result<()> {
-    let sys_hosts_path = get_sys_hosts_path();
-    let arg_str: String = format!(
-        r#"echo "{}" | sudo -S chmod 777 {}"#,
-        password, sys_hosts_path
-    );
-    let output = Command::new("sh").arg("-c").arg(arg_str).output()?;
+/// 写入前备份当前的系统 hosts，备份失败不影响写入；其他目标不备份
+fn backup_if_sys_hosts(path: &str) {
+    if path != get_sys_hosts_path() {
+        return;
+    }
+    let res = fs::read(path)
+        .map_err(|e| e.into())
+        .and_then(|content| create_backup(&content, read_settings().backup_retention));
+    if let Err(e) = res {
+        error!("{e}");
+    }
+}
 
-    if !output.status.success() {
-        return Err(color_eyre::eyre::Error::msg(
-            "Failed to execute sudo command",
-        ));
+/// 原文件无法读取时不写入，避免覆盖掉无法确认的内容；原文件中 localhost 的映射在写入后必须仍然存在
+pub fn check_before_write(path: &str, content: &[u8]) -> Result<()> {
+    let current = match fs::read(path) {
+        Ok(current) => current,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => {
+            error!("{path}: {e}");
+            return Err(color_eyre::eyre::Error::msg("unreadable hosts"));
+        }
+    };
+    let remaining = essential_entries(content);
+    let missing = essential_entries(&current)
+        .into_iter()
+        .filter(|entry| !remaining.contains(entry))
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        error!("{path}: essential entries missing {:?}", missing);
+        return Err(color_eyre::eyre::Error::msg("essential entries missing"));
     }
     Ok(())
 }
 
-pub fn resume_permissions(
-    password: &(impl Into<String> + Display),
-    old_permission_mode: &str,
-) -> Result<()> {
-    let sys_hosts_path = get_sys_hosts_path();
-    let arg_str: String = format!(
-        r#"echo "{}" | sudo -S chmod {} {}"#,
-        password, old_permission_mode, sys_hosts_path
-    );
-    let output = Command::new("sh").arg("-c").arg(arg_str).output()?;
+/// 127.0.0.1 和 ::1 到 localhost 的映射
+fn essential_entries(content: &[u8]) -> Vec<(String, String)> {
+    let mut entries = vec![];
+    let text = TextFormat::detect(content).decode(content);
+    for (_, entry) in HostsDocument::parse(&text).active_entries() {
+        if !ESSENTIAL_IPS.contains(&entry.ip.as_str()) {
+            continue;
+        }
+        for hostname in entry.hostnames.iter().filter(|name| *name == "localhost") {
+            let pair = (entry.ip.clone(), hostname.clone());
+            if !entries.contains(&pair) {
+                entries.push(pair);
+            }
+        }
+    }
+    entries
+}
 
-    if !output.status.success() {
-        return Err(color_eyre::eyre::Error::msg(
-            "Failed to execute sudo command",
-        ));
+pub fn generate_sys_hosts_content(appended: String) -> Result<Vec<u8>> {
+    generate_hosts_file_content(&Target::system(), &appended)
+}
+
+/// 生成目标 hosts 文件写入后的完整内容，原文件无法读取时返回错误，不会生成只有受管理区块的内容
+pub fn generate_hosts_file_content(target: &Target, appended: &str) -> Result<Vec<u8>> {
+    let content = read_hosts_bytes(&target.path)?;
+    let markers = target.block_markers()?;
+    Ok(edit_hosts_bytes(content, |content, line_ending| {
+        replace_managed_block(content, appended, line_ending, &markers)
+    }))
+}
+
+/// 去掉目标 hosts 文件中的受管理区块（包括旧标记的区块）后的完整内容，没有区块时返回 None
+pub fn generate_removed_content(target: &Target) -> Result<Option<Vec<u8>>> {
+    let content = read_hosts_bytes(&target.path)?;
+    let markers = target.block_markers()?;
+    let removed = edit_hosts_bytes(content.clone(), |content, _| {
+        remove_managed_block(content, &markers)
+    });
+    Ok(Some(removed).filter(|removed| *removed != content))
+}
+
+/// 把 `lines` 从受管理区块以外删除并写入 `appended` 后的完整内容。
+/// `lines` 为 (行号, 内容)，文件在选择之后被修改过、行号与内容对不上时返回 `hosts changed`
+pub fn generate_adopted_content(
+    target: &Target,
+    lines: &[(usize, String)],
+    appended: &str,
+) -> Result<Vec<u8>> {
+    let content = read_hosts_bytes(&target.path)?;
+    let markers = target.block_markers()?;
+    let text = TextFormat::detect(&content).decode(&content);
+    let unmanaged = unmanaged_lines(&text, &markers);
+    if lines.iter().any(|line| !unmanaged.contains(line)) {
+        return Err(color_eyre::eyre::Error::msg("hosts changed"));
     }
-    Ok(())
+    Ok(edit_hosts_bytes(content, |content, line_ending| {
+        let kept = content
+            .split_inclusive(|byte| *byte == b'\n')
+            .enumerate()
+            .filter(|(index, _)| !lines.iter().any(|(line, _)| line == index))
+            .flat_map(|(_, line)| line.iter().copied())
+            .collect();
+        replace_managed_block(kept, appended, line_ending, &markers)
+    }))
+}
+
+/// 在编辑器中修改后的完整内容。受管理区块（包括起止标记）必须与文件中的完全一致，否则返回 `managed block changed`；
+/// 文件不能按检测到的编码无损解码时返回 `undecodable hosts`，避免把无法解码的字节写成替换字符。
+/// 编码和 BOM 与原文件一致，没有修改的行保留原来的换行符，新增和修改的行使用原文件中较多的一种
+pub fn generate_edited_content(target: &Target, edited: &str) -> Result<Vec<u8>> {
+    let content = read_hosts_bytes(&target.path)?;
+    let format = TextFormat::detect(&content);
+    let current = format
+        .decode_strict(&content)
+        .ok_or(color_eyre::eyre::Error::msg("undecodable hosts"))?;
+    let markers = target.block_markers()?;
+    let edited = normalize_line_endings(edited);
+    if managed_block_lines(&normalize_line_endings(&current), &markers)
+        != managed_block_lines(&edited, &markers)
+    {
+        return Err(color_eyre::eyre::Error::msg("managed block changed"));
+    }
+    Ok(format.encode(&merge_line_endings(&current, &edited, format.line_ending)))
 }
 
-pub fn generate_sys_hosts_content(appended: String) -> String {
-    let mut content = read_sys_hosts().unwrap();
-    let start_index = content.find(CONTENT_START);
-    let end_index = content.find(CONTENT_END);
-    match [start_index, end_index] {
-        [Some(start), Some(end)] => {
-            let mut new_appended = String::from("\n");
-            new_appended.push_str(&appended);
-            new_appended.push_str("\n");
-            content.replace_range((start + CONTENT_START.len())..end, new_appended.as_str());
+/// 检查目标 hosts 文件能否按检测到的编码无损解码，不能时返回 `undecodable hosts`
+pub fn check_hosts_decodable(target: &Target) -> Result<()> {
+    let content = read_hosts_bytes(&target.path)?;
+    match TextFormat::detect(&content).decode_strict(&content) {
+        Some(_) => Ok(()),
+        None => Err(color_eyre::eyre::Error::msg("undecodable hosts")),
+    }
+}
+
+/// 把只用 `\n` 换行的 `edited` 按行与 `current` 比较，相同的行保留 `current` 中原来的换行符
+fn merge_line_endings(current: &str, edited: &str, line_ending: LineEnding) -> String {
+    let old = split_line_endings(current);
+    let new = split_line_endings(edited);
+    let old_bodies = old.iter().map(|(body, _)| *body).collect::<Vec<_>>();
+    let new_bodies = new.iter().map(|(body, _)| *body).collect::<Vec<_>>();
+    let mut merged = String::new();
+    for op in capture_diff_slices(Algorithm::Myers, &old_bodies, &new_bodies) {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        for (offset, (body, ending)) in new[new_range].iter().enumerate() {
+            merged.push_str(body);
+            if ending.is_empty() {
+                continue;
+            }
+            match old.get(old_range.start + offset) {
+                Some((_, old_ending)) if tag == DiffTag::Equal && !old_ending.is_empty() => {
+                    merged.push_str(old_ending)
+                }
+                _ => merged.push_str(line_ending.as_str()),
+            }
         }
-        _ => {
-            content.push_str(&format!(
-                "\n\n{}\n{}\n{}\n",
-                CONTENT_START, appended, CONTENT_END
-            ));
+    }
+    merged
+}
+
+/// 把文本拆分为 (行的内容, 换行符)，最后一行没有换行符时为空
+fn split_line_endings(text: &str) -> Vec<(&str, &str)> {
+    text.split_inclusive('\n')
+        .map(|line| {
+            let body = line.trim_end_matches('\n').trim_end_matches('\r');
+            (body, &line[body.len()..])
+        })
+        .collect()
+}
+
+/// 受管理区块占用的行，返回 (起始标记所在行, 结束标记所在行)，行号从 0 开始
+pub fn managed_block_rows(text: &str, markers: &[BlockMarker]) -> Option<(usize, usize)> {
+    markers.iter().find_map(|marker| {
+        let (start, end) = marker.find(text.as_bytes())?;
+        Some((
+            text[..start].matches('\n').count(),
+            text[..end].matches('\n').count(),
+        ))
+    })
+}
+
+fn managed_block_lines<'a>(text: &'a str, markers: &[BlockMarker]) -> Option<Vec<&'a str>> {
+    let (start, end) = managed_block_rows(text, markers)?;
+    Some(text.lines().skip(start).take(end + 1 - start).collect())
+}
+
+/// 受管理区块以外的非空行，返回 (行号, 去掉换行符的内容)，行号从 0 开始
+fn unmanaged_lines(text: &str, markers: &[BlockMarker]) -> Vec<(usize, String)> {
+    let block = markers.iter().find_map(|marker| {
+        marker
+            .find(text.as_bytes())
+            .map(|(start, end)| (start, end + marker.end.len()))
+    });
+    let mut offset = 0;
+    let mut lines = vec![];
+    for (index, line) in text.split_inclusive('\n').enumerate() {
+        let start = offset;
+        offset += line.len();
+        if matches!(block, Some((block_start, block_end)) if start < block_end && offset > block_start)
+        {
+            continue;
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        if !line.trim().is_empty() {
+            lines.push((index, line.to_owned()));
+        }
+    }
+    lines
+}
+
+fn read_hosts_bytes(path: &str) -> Result<Vec<u8>> {
+    fs::read(path).map_err(|e| {
+        error!("{path}: {e}");
+        color_eyre::eyre::Error::msg("unreadable hosts")
+    })
+}
+
+/// UTF-8 的文件直接按字节修改，其他编码先解码，修改后再按原编码写回
+fn edit_hosts_bytes(
+    content: Vec<u8>,
+    edit: impl FnOnce(Vec<u8>, LineEnding) -> Vec<u8>,
+) -> Vec<u8> {
+    let format = TextFormat::detect(&content);
+    if format.encoding == Encoding::Utf8 {
+        return edit(content, format.line_ending);
+    }
+    let edited = edit(format.decode(&content).into_bytes(), format.line_ending);
+    format.encode(&String::from_utf8_lossy(&edited))
+}
+
+/// 用 `appended` 替换受管理区块的内容，`markers` 中第一个是当前的标记，其余是需要迁移的旧标记：
+/// 只有旧标记的区块时整个替换为当前标记的区块，都没有时追加到末尾。
+/// 按字节处理，区块以外的内容（包括 BOM、非 UTF-8 的字节和混用的换行符）原样保留，
+/// 区块内使用原文件的换行符
+fn replace_managed_block(
+    mut content: Vec<u8>,
+    appended: &str,
+    line_ending: LineEnding,
+    markers: &[BlockMarker],
+) -> Vec<u8> {
+    let newline = line_ending.as_str();
+    let appended = line_ending.apply(appended);
+    let current = &markers[0];
+    if let Some((start, end)) = current.find(&content) {
+        let new_appended = format!("{newline}{appended}{newline}");
+        content.splice((start + current.start.len())..end, new_appended.bytes());
+        return content;
+    }
+    let block = format!(
+        "{}{newline}{appended}{newline}{}",
+        current.start, current.end
+    );
+    match markers[1..]
+        .iter()
+        .find_map(|marker| marker.find(&content).map(|found| (marker, found)))
+    {
+        Some((legacy, (start, end))) => {
+            content.splice(start..end + legacy.end.len(), block.bytes());
+        }
+        None => {
+            content.extend_from_slice(format!("{newline}{newline}{block}{newline}").as_bytes());
         }
     }
     content
 }
 
-pub fn write_sys_hosts_with_sudo(password: String, appended: String) -> Result<()> {
-    check_password_correct(password, move || {
-        let sys_hosts_path = get_sys_hosts_path();
-        let hosts_content = generate_sys_hosts_content(appended.clone());
-        fs::write(&sys_hosts_path, &hosts_content);
-    });
-    Ok(())
+/// 去掉所有标记的受管理区块，以及写入区块时在前面加上的空行
+fn remove_managed_block(mut content: Vec<u8>, markers: &[BlockMarker]) -> Vec<u8> {
+    for marker in markers {
+        while let Some((start, end)) = marker.find(&content) {
+            let mut begin = start;
+            loop {
+                let before = &content[..begin];
+                match before
+                    .strip_suffix(b"\r\n")
+                    .or_else(|| before.strip_suffix(b"\n"))
+                {
+                    Some(trimmed) if trimmed.is_empty() || trimmed.ends_with(b"\n") => {
+                        begin = trimmed.len()
+                    }
+                    _ => break,
+                }
+            }
+            let mut finish = end + marker.end.len();
+            let after = &content[finish..];
+            if after.starts_with(b"\r\n") {
+                finish += 2;
+            } else if after.starts_with(b"\n") {
+                finish += 1;
+            }
+            content.drain(begin..finish);
+        }
+    }
+    content
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+pub fn write_sys_hosts_elevated(password: Option<&str>, appended: String) -> Result<()> {
+    let hosts_content = generate_sys_hosts_content(appended)?;
+    replace_sys_hosts_elevated(password, hosts_content)
+}
+
+/// 通过设置中的提权方式覆盖系统 hosts，内容经 stdin 传给提权后的 tee，不会修改文件权限
+pub fn replace_sys_hosts_elevated(password: Option<&str>, content: Vec<u8>) -> Result<()> {
+    replace_hosts_file_elevated(&get_sys_hosts_path(), password, content)
+}
+
+fn replace_hosts_file_elevated(path: &str, password: Option<&str>, content: Vec<u8>) -> Result<()> {
+    check_before_write(path, &content)?;
+    backup_if_sys_hosts(path);
+    read_settings()
+        .privileged_writer()
+        .write(path, &content, password)
+}
+
+pub fn replace_sys_hosts_with_password(password: Option<&str>, content: Vec<u8>) -> Result<()> {
+    replace_hosts_file_with_password(&get_sys_hosts_path(), password, content)
+}
+
+/// 先直接写入，没有权限时再提权写入。sudo 需要密码，没有密码且没有配置 askpass 时不会尝试提权
+pub fn replace_hosts_file_with_password(
+    path: &str,
+    password: Option<&str>,
+    content: Vec<u8>,
+) -> Result<()> {
+    if cfg!(target_os = "windows") {
+        return replace_hosts_file(path, content);
+    }
+    if password.is_some() {
+        return replace_hosts_file_elevated(path, password, content);
+    }
+    match replace_hosts_file(path, content.clone()) {
+        Err(e) if is_unsafe_write(&e) => Err(e),
+        Err(e) if !read_settings().privileged_writer().needs_password() => {
+            error!("{e}");
+            replace_hosts_file_elevated(path, None, content)
+        }
+        res => res,
+    }
+}
+
+/// 只验证密码是否正确，不会执行命令或修改任何文件
+pub fn check_password_correct(password: &str) -> Result<()> {
+    if cfg!(target_os = "windows") {
+        return Ok(());
+    }
+    read_settings().privileged_writer().check_password(password)
+}
+
+/// 系统 hosts 中不受本工具管理的部分，即去掉受管理区块后的文本
+pub fn read_unmanaged_sys_hosts() -> Result<String> {
+    let content = read_sys_hosts()?.into_bytes();
+    let removed = remove_managed_block(content, &Target::system().block_markers()?);
+    Ok(String::from_utf8_lossy(&removed).into_owned())
+}
+
+/// 系统 hosts 中受管理区块以外的非空行，用于选择导入到 hosts 中的行
+pub fn read_unmanaged_sys_lines() -> Result<Vec<(usize, String)>> {
+    Ok(unmanaged_lines(
+        &read_sys_hosts()?,
+        &Target::system().block_markers()?,
+    ))
+}
+
+/// 受管理区块起止标记之间的内容，依次尝试 `markers` 中的标记，都没有时返回 None
+pub fn extract_managed_block<'a>(content: &'a str, markers: &[BlockMarker]) -> Option<&'a str> {
+    markers.iter().find_map(|marker| {
+        let (start, end) = marker.find(content.as_bytes())?;
+        content.get(start + marker.start.len()..end)
+    })
+}
+
+/// 写入前的安全检查没有通过，这类错误不是权限问题，提权后重试也不会成功
+pub fn is_unsafe_write(e: &color_eyre::eyre::Error) -> bool {
+    let message = e.to_string();
+    message == "unreadable hosts" || message == "essential entries missing"
+}
+
+/// 记录写入 `name` 时的错误。只有没有权限或者提权认证失败才返回 `no permission`，由调用方请求密码，
+/// 写入前的安全检查和其他错误（如磁盘已满、路径无效、提权后的脚本失败）原样返回
+pub fn write_error(name: &str, e: color_eyre::eyre::Error) -> color_eyre::eyre::Error {
+    error!("{name}: {e}");
+    let denied = e
+        .downcast_ref::<std::io::Error>()
+        .is_some_and(|e| e.kind() == ErrorKind::PermissionDenied)
+        || matches!(e.to_string().as_str(), "wrong password" | "not in sudoers");
+    if denied {
+        color_eyre::eyre::Error::msg("no permission")
+    } else {
+        e
+    }
+}
+
+/// 读取 hosts 文件用于展示和比较，文件不存在或无法读取时返回错误。
+/// 去掉 BOM 并按原文件的编码解码，无法解码的字节显示为替换字符
+pub fn read_hosts_file(path: &str) -> Result<String> {
+    let buf = fs::read(path)?;
+    Ok(TextFormat::detect(&buf).decode(&buf))
 }
 
 pub fn read_sys_hosts() -> Result<String> {
-    let hosts_path = get_sys_hosts_path();
-    let content = fs::read(&hosts_path)
-        .map(|buf| String::from_utf8(buf).unwrap_or(String::new()))
-        .unwrap();
-    Ok(content)
+    read_hosts_file(&get_sys_hosts_path())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT_START: &str = "# --- SWITCHHOSTS_RS_CONTENT_START ---";
+
+    const CONTENT_END: &str = "# --- SWITCHHOSTS_RS_CONTENT_END ---";
+
+    fn target(path: &str) -> Target {
+        Target {
+            name: "test".to_owned(),
+            path: path.to_owned(),
+            ..Target::default()
+        }
+    }
+
+    #[test]
+    fn test_write_error() {
+        let denied = std::io::Error::from(ErrorKind::PermissionDenied);
+        assert_eq!(
+            write_error("test", denied.into()).to_string(),
+            "no permission"
+        );
+        for message in ["wrong password", "not in sudoers"] {
+            let e = color_eyre::eyre::Error::msg(message);
+            assert_eq!(write_error("test", e).to_string(), "no permission");
+        }
+        let full = std::io::Error::from(ErrorKind::StorageFull);
+        assert_eq!(
+            write_error("test", full.into()).to_string(),
+            "no storage space"
+        );
+        for message in [
+            "essential entries missing",
+            "elevated write failed: mv: busy",
+        ] {
+            let e = color_eyre::eyre::Error::msg(message);
+            assert_eq!(write_error("test", e).to_string(), message);
+        }
+    }
+
+    #[test]
+    fn test_extract_managed_block() {
+        let content = format!(
+            "127.0.0.1 localhost\n{}\n10.0.0.1 a.test\n{}\n",
+            CONTENT_START, CONTENT_END
+        );
+        assert_eq!(
+            extract_managed_block(&content, &[BlockMarker::default()]),
+            Some("\n10.0.0.1 a.test\n")
+        );
+        assert_eq!(
+            extract_managed_block("127.0.0.1 localhost\n", &[BlockMarker::default()]),
+            None
+        );
+        let reversed = format!("{}\n{}\n", CONTENT_END, CONTENT_START);
+        assert_eq!(
+            extract_managed_block(&reversed, &[BlockMarker::default()]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_replace_managed_block_keeps_bytes() {
+        let mut content = b"127.0.0.1 localhost\n# caf\xe9\n".to_vec();
+        let replaced = replace_managed_block(
+            content.clone(),
+            "10.0.0.1 a.test",
+            LineEnding::Lf,
+            &[BlockMarker::default()],
+        );
+        assert!(replaced.starts_with(&content));
+        let replaced = replace_managed_block(
+            replaced,
+            "10.0.0.2 b.test",
+            LineEnding::Lf,
+            &[BlockMarker::default()],
+        );
+        assert!(replaced.starts_with(&content));
+        content.extend_from_slice(
+            format!("\n\n{}\n10.0.0.2 b.test\n{}\n", CONTENT_START, CONTENT_END).as_bytes(),
+        );
+        assert_eq!(replaced, content);
+    }
+
+    #[test]
+    fn test_keep_line_endings() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("hosts");
+        let path_str = path.to_string_lossy().into_owned();
+
+        fs::write(&path, "127.0.0.1 localhost\r\n::1 localhost\r\n")?;
+        let content =
+            generate_hosts_file_content(&target(&path_str), "10.0.0.1 a.test\n10.0.0.2 b.test")?;
+        let expected = format!(
+            "127.0.0.1 localhost\r\n::1 localhost\r\n\r\n\r\n{}\r\n10.0.0.1 a.test\r\n10.0.0.2 b.test\r\n{}\r\n",
+            CONTENT_START, CONTENT_END
+        );
+        assert_eq!(String::from_utf8(content.clone()).unwrap(), expected);
+        fs::write(&path, &content)?;
+        let content = generate_hosts_file_content(&target(&path_str), "10.0.0.3 c.test")?;
+        assert!(!String::from_utf8(content).unwrap().contains("c.test\n"));
+
+        // 换行符混用时区块以外保持原样，区块内使用较多的一种
+        let mixed = format!(
+            "\u{feff}127.0.0.1 localhost\r\n# lf\n::1 localhost\r\n# a\r\n# b\r\n# c\r\n{}\n10.0.0.1 a.test\n{}\n",
+            CONTENT_START, CONTENT_END
+        );
+        fs::write(&path, &mixed)?;
+        let content = String::from_utf8(generate_hosts_file_content(
+            &target(&path_str),
+            "10.0.0.3 c.test",
+        )?)?;
+        assert_eq!(
+            content,
+            format!(
+                "\u{feff}127.0.0.1 localhost\r\n# lf\n::1 localhost\r\n# a\r\n# b\r\n# c\r\n{}\r\n10.0.0.3 c.test\r\n{}\n",
+                CONTENT_START, CONTENT_END
+            )
+        );
+
+        // UTF-16 的文件按原编码写回
+        let format = TextFormat {
+            encoding: Encoding::Utf16Le,
+            bom: true,
+            line_ending: LineEnding::CrLf,
+        };
+        fs::write(&path, format.encode("127.0.0.1 localhost\r\n"))?;
+        let content = generate_hosts_file_content(&target(&path_str), "10.0.0.1 a.test")?;
+        assert_eq!(TextFormat::detect(&content), format);
+        assert!(format.decode(&content).contains("\r\n10.0.0.1 a.test\r\n"));
+        fs::write(&path, &content)?;
+        assert!(read_hosts_file(&path_str)?.starts_with("127.0.0.1 localhost\r\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_before_write() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("hosts");
+        let path_str = path.to_string_lossy().into_owned();
+        assert!(check_before_write(&path_str, b"").is_ok());
+
+        fs::write(&path, "127.0.0.1 localhost\n::1 localhost ip6-localhost\n")?;
+        assert!(check_before_write(&path_str, b"127.0.0.1 localhost\n::1 localhost\n").is_ok());
+        let res = check_before_write(&path_str, b"127.0.0.1 localhost\n# ::1 localhost\n");
+        assert_eq!(res.unwrap_err().to_string(), "essential entries missing");
+
+        // 原文件没有 localhost 时不做要求
+        fs::write(&path, "10.0.0.1 a.test\n")?;
+        assert!(check_before_write(&path_str, b"").is_ok());
+
+        // 目录无法作为文件读取
+        let res = check_before_write(&dir.path().to_string_lossy(), b"");
+        assert_eq!(res.unwrap_err().to_string(), "unreadable hosts");
+        let res =
+            generate_hosts_file_content(&target(&dir.path().join("missing").to_string_lossy()), "");
+        assert_eq!(res.unwrap_err().to_string(), "unreadable hosts");
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_markers() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("hosts");
+        let mut target = target(&path.to_string_lossy());
+        let other = "# --- OTHER_START ---\n10.9.9.9 other.test\n# --- OTHER_END ---\n";
+        fs::write(&path, format!("127.0.0.1 localhost\n{other}"))?;
+
+        // 其他工具的区块不受影响
+        target.marker = Some("MINE".to_owned());
+        let content = String::from_utf8(generate_hosts_file_content(&target, "10.0.0.1 a.test")?)?;
+        assert_eq!(
+            content,
+            format!("127.0.0.1 localhost\n{other}\n\n# --- MINE_START ---\n10.0.0.1 a.test\n# --- MINE_END ---\n")
+        );
+
+        // 旧标记的区块原地改为当前的标记
+        fs::write(&path, &content)?;
+        target.marker = Some("NEW".to_owned());
+        target.migrate_markers = vec!["MINE".to_owned()];
+        let content = String::from_utf8(generate_hosts_file_content(&target, "10.0.0.2 b.test")?)?;
+        assert_eq!(
+            content,
+            format!("127.0.0.1 localhost\n{other}\n\n# --- NEW_START ---\n10.0.0.2 b.test\n# --- NEW_END ---\n")
+        );
+        assert_eq!(
+            extract_managed_block(&content, &target.block_markers()?),
+            Some("\n10.0.0.2 b.test\n")
+        );
+
+        fs::write(&path, &content)?;
+        let removed = generate_removed_content(&target)?.map(String::from_utf8);
+        assert_eq!(removed, Some(Ok(format!("127.0.0.1 localhost\n{other}"))));
+        fs::write(&path, format!("127.0.0.1 localhost\n{other}"))?;
+        assert_eq!(generate_removed_content(&target)?, None);
+
+        // 名称无效时不写入，不能退回到默认的标记
+        assert_eq!(
+            BlockMarker::new("bad marker").unwrap_err().to_string(),
+            "invalid marker"
+        );
+        target.marker = Some("bad marker".to_owned());
+        let res = generate_hosts_file_content(&target, "10.0.0.3 c.test");
+        assert_eq!(res.unwrap_err().to_string(), "invalid marker");
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate_adopted_content() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("hosts");
+        let target = target(&path.to_string_lossy());
+        fs::write(
+            &path,
+            format!("127.0.0.1 localhost\r\n10.0.0.1 a.test\r\n\r\n{CONTENT_START}\r\n10.0.0.2 b.test\r\n{CONTENT_END}\r\n"),
+        )?;
+        let text = fs::read_to_string(&path)?;
+        let lines = unmanaged_lines(&text, &target.block_markers()?);
+        assert_eq!(
+            lines,
+            vec![
+                (0, "127.0.0.1 localhost".to_owned()),
+                (1, "10.0.0.1 a.test".to_owned())
+            ]
+        );
+
+        let content =
+            generate_adopted_content(&target, &lines[1..], "10.0.0.2 b.test\n10.0.0.1 a.test")?;
+        assert_eq!(
+            String::from_utf8(content)?,
+            format!("127.0.0.1 localhost\r\n\r\n{CONTENT_START}\r\n10.0.0.2 b.test\r\n10.0.0.1 a.test\r\n{CONTENT_END}\r\n")
+        );
+
+        // 选择之后文件被修改过
+        let res = generate_adopted_content(&target, &[(1, "10.0.0.9 z.test".to_owned())], "");
+        assert_eq!(res.unwrap_err().to_string(), "hosts changed");
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate_edited_content() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("hosts");
+        let target = target(&path.to_string_lossy());
+        fs::write(
+            &path,
+            format!("127.0.0.1 localhost\r\n\r\n{CONTENT_START}\r\n10.0.0.2 b.test\r\n{CONTENT_END}\r\n"),
+        )?;
+        let text = normalize_line_endings(&fs::read_to_string(&path)?);
+        assert_eq!(
+            managed_block_rows(&text, &target.block_markers()?),
+            Some((2, 4))
+        );
+
+        let edited = text.replace(
+            "127.0.0.1 localhost\n",
+            "127.0.0.1 localhost\n10.0.0.1 a.test\n",
+        );
+        assert_eq!(
+            String::from_utf8(generate_edited_content(&target, &edited)?)?,
+            format!("127.0.0.1 localhost\r\n10.0.0.1 a.test\r\n\r\n{CONTENT_START}\r\n10.0.0.2 b.test\r\n{CONTENT_END}\r\n")
+        );
+
+        // 受管理区块只能通过启用、停用 hosts 修改
+        for edited in [
+            text.replace("10.0.0.2", "10.0.0.3"),
+            text.replace(&format!("{CONTENT_END}\n"), ""),
+        ] {
+            let res = generate_edited_content(&target, &edited);
+            assert_eq!(res.unwrap_err().to_string(), "managed block changed");
+        }
+
+        // 没有修改的行保留原来的换行符，新增的行使用较多的一种
+        fs::write(
+            &path,
+            format!(
+                "127.0.0.1 localhost\r\n::1 localhost\n\r\n{CONTENT_START}\r\n{CONTENT_END}\r\n"
+            ),
+        )?;
+        let text = normalize_line_endings(&fs::read_to_string(&path)?);
+        let edited = text.replace("::1 localhost\n", "::1 localhost\n10.0.0.1 a.test\n");
+        assert_eq!(
+            String::from_utf8(generate_edited_content(&target, &edited)?)?,
+            format!("127.0.0.1 localhost\r\n::1 localhost\n10.0.0.1 a.test\r\n\r\n{CONTENT_START}\r\n{CONTENT_END}\r\n")
+        );
+
+        // 无法解码的字节不能写成替换字符
+        fs::write(&path, b"127.0.0.1 localhost\n# caf\xe9\n")?;
+        assert!(check_hosts_decodable(&target).is_err());
+        let res = generate_edited_content(&target, "127.0.0.1 localhost\n# caf\u{fffd}\n# x\n");
+        assert_eq!(res.unwrap_err().to_string(), "undecodable hosts");
+        Ok(())
+    }
 }
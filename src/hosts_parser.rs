@@ -0,0 +1,240 @@
+use std::fmt::{self, Display};
+
+/// 一条 hosts 映射，`disabled` 表示被 `#` 注释掉的映射
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HostsEntry {
+    pub ip: String,
+    pub hostnames: Vec<String>,
+    pub comment: Option<String>,
+    pub disabled: bool,
+}
+
+impl HostsEntry {
+    pub fn new(ip: impl Into<String>, hostnames: Vec<String>) -> Self {
+        HostsEntry {
+            ip: ip.into(),
+            hostnames,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.disabled
+    }
+
+    /// 按标准格式生成一行文本，不包含换行符
+    pub fn to_line(&self) -> String {
+        let mut line = String::new();
+        if self.disabled {
+            line.push_str("# ");
+        }
+        line.push_str(&self.ip);
+        for hostname in &self.hostnames {
+            line.push(' ');
+            line.push_str(hostname);
+        }
+        if let Some(comment) = &self.comment {
+            line.push_str(" #");
+            line.push_str(comment);
+        }
+        line
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum HostsLineKind {
+    Blank,
+    Comment(String),
+    Entry(HostsEntry),
+}
+
+/// hosts 文件中的一行，`raw` 和 `ending` 保留原始文本，用于无损还原
+#[derive(Clone, Debug, PartialEq)]
+pub struct HostsLine {
+    pub line_number: usize,
+    pub raw: String,
+    pub ending: String,
+    pub kind: HostsLineKind,
+}
+
+impl HostsLine {
+    pub fn entry(&self) -> Option<&HostsEntry> {
+        match &self.kind {
+            HostsLineKind::Entry(entry) => Some(entry),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HostsDocument {
+    pub lines: Vec<HostsLine>,
+}
+
+impl HostsDocument {
+    pub fn parse(content: &str) -> Self {
+        let lines = content
+            .split_inclusive('\n')
+            .enumerate()
+            .map(|(idx, segment)| {
+                let (raw, ending) = split_line_ending(segment);
+                HostsLine {
+                    line_number: idx + 1,
+                    raw: raw.to_owned(),
+                    ending: ending.to_owned(),
+                    kind: parse_line(raw),
+                }
+            })
+            .collect();
+        HostsDocument { lines }
+    }
+
+    /// 所有映射，包括被注释掉的
+    pub fn entries(&self) -> impl Iterator<Item = (&HostsLine, &HostsEntry)> {
+        self.lines
+            .iter()
+            .filter_map(|line| line.entry().map(|entry| (line, entry)))
+    }
+
+    /// 生效中的映射
+    pub fn active_entries(&self) -> impl Iterator<Item = (&HostsLine, &HostsEntry)> {
+        self.entries().filter(|(_, entry)| entry.is_active())
+    }
+
+    /// 在末尾追加一条映射
+    pub fn push_entry(&mut self, entry: HostsEntry) {
+        let ending = self
+            .lines
+            .first()
+            .map(|line| line.ending.clone())
+            .filter(|ending| !ending.is_empty())
+            .unwrap_or("\n".to_owned());
+        if let Some(last) = self.lines.last_mut() {
+            if last.ending.is_empty() {
+                last.ending = ending.clone();
+            }
+        }
+        self.lines.push(HostsLine {
+            line_number: self.lines.len() + 1,
+            raw: entry.to_line(),
+            ending,
+            kind: HostsLineKind::Entry(entry),
+        });
+    }
+}
+
+impl Display for HostsDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            f.write_str(&line.raw)?;
+            f.write_str(&line.ending)?;
+        }
+        Ok(())
+    }
+}
+
+fn split_line_ending(segment: &str) -> (&str, &str) {
+    if let Some(raw) = segment.strip_suffix("\r\n") {
+        (raw, "\r\n")
+    } else if let Some(raw) = segment.strip_suffix('\n') {
+        (raw, "\n")
+    } else {
+        (segment, "")
+    }
+}
+
+pub fn parse_line(raw: &str) -> HostsLineKind {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return HostsLineKind::Blank;
+    }
+    if let Some(commented) = trimmed.strip_prefix('#') {
+        return match parse_entry(commented) {
+            Some(entry) if looks_like_ip(&entry.ip) && !entry.hostnames.is_empty() => {
+                HostsLineKind::Entry(HostsEntry {
+                    disabled: true,
+                    ..entry
+                })
+            }
+            _ => HostsLineKind::Comment(commented.to_owned()),
+        };
+    }
+    match parse_entry(trimmed) {
+        Some(entry) => HostsLineKind::Entry(entry),
+        None => HostsLineKind::Blank,
+    }
+}
+
+fn parse_entry(text: &str) -> Option<HostsEntry> {
+    let (body, comment) = match text.find('#') {
+        Some(idx) => (&text[..idx], Some(text[idx + 1..].to_owned())),
+        None => (text, None),
+    };
+    let mut tokens = body.split_whitespace();
+    let ip = tokens.next()?;
+    Some(HostsEntry {
+        ip: ip.to_owned(),
+        hostnames: tokens.map(|token| token.to_owned()).collect(),
+        comment,
+        disabled: false,
+    })
+}
+
+/// 粗略判断，用于区分被注释掉的映射和普通注释，不做合法性校验
+fn looks_like_ip(token: &str) -> bool {
+    (token.contains('.') || token.contains(':'))
+        && token
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() || c == '.' || c == ':' || c == '%')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hosts() {
+        let content = "# comment\n127.0.0.1 localhost dev.local # local\n\n# 10.0.0.1 api.dev\n::1\tlocalhost\n";
+        let doc = HostsDocument::parse(content);
+        assert_eq!(doc.lines.len(), 5);
+        assert_eq!(doc.lines[0].kind, HostsLineKind::Comment(" comment".to_owned()));
+        assert_eq!(
+            doc.lines[1].kind,
+            HostsLineKind::Entry(HostsEntry {
+                ip: "127.0.0.1".to_owned(),
+                hostnames: vec!["localhost".to_owned(), "dev.local".to_owned()],
+                comment: Some(" local".to_owned()),
+                disabled: false,
+            })
+        );
+        assert_eq!(doc.lines[2].kind, HostsLineKind::Blank);
+        let disabled = doc.lines[3].entry().unwrap();
+        assert!(disabled.disabled);
+        assert_eq!(disabled.hostnames, vec!["api.dev".to_owned()]);
+        assert_eq!(doc.lines[4].line_number, 5);
+        assert_eq!(doc.active_entries().count(), 2);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let contents = [
+            "127.0.0.1 localhost\n10.0.0.1   api.dev\t# tab\n",
+            "127.0.0.1 localhost\r\n\r\n# note\r\n10.0.0.1 api.dev",
+            "  # indented\n127.0.0.1dev.local\n\n\n",
+            "",
+        ];
+        for content in contents {
+            assert_eq!(HostsDocument::parse(content).to_string(), content);
+        }
+    }
+
+    #[test]
+    fn test_push_entry() {
+        let mut doc = HostsDocument::parse("127.0.0.1 localhost\r\n# end");
+        doc.push_entry(HostsEntry::new("10.0.0.1", vec!["api.dev".to_owned()]));
+        assert_eq!(
+            doc.to_string(),
+            "127.0.0.1 localhost\r\n# end\r\n10.0.0.1 api.dev\r\n"
+        );
+    }
+}
@@ -1,27 +1,124 @@
+use crate::conflict::{find_conflicts, Conflict, HostsSource};
 use crate::data::{
-    add_item, delete_item, deserialize_and_write_config, read_config, read_item_data,
-    update_config_item, ConfigItem, ConfigItemType,
+    add_item, delete_item, deserialize_and_write_config, read_config, read_hosts_content,
+    read_hosts_modified, read_item_data, save_config_item, update_config_item, write_item_data,
+    ConfigItem, ConfigItemType, RemoteSource,
+};
+use crate::hosts::{
+    check_before_write, extract_managed_block, generate_adopted_content, generate_edited_content,
+    generate_hosts_file_content, generate_removed_content, generate_sys_hosts_content,
+    read_sys_hosts, read_unmanaged_sys_hosts, replace_hosts_file_with_password, write_error,
 };
-use crate::hosts::{write_sys_hosts, write_sys_hosts_with_sudo};
 use crate::observer::Subject;
+use crate::provenance::{normalize_block, strip_provenance, wrap_profile};
+use crate::remote::{apply_fetch_result, FetchResult};
+use crate::settings::read_settings;
+use crate::target::{get_targets, Target, SYSTEM_TARGET};
 use crate::util::Result;
 use crate::util::{find_config_by_id, find_mut_config_by_id, find_selected_index};
-use log::debug;
+use crate::validator::validate_hosts;
+use log::{debug, error};
 use ratatui::{
     prelude::{Buffer, Rect},
     style::{Style, Stylize},
+    text::Line,
     widgets::{Block, List, ListItem, ListState, StatefulWidget, Widget},
 };
 use std::cell::RefCell;
+use std::fs;
 use std::rc::Rc;
 use uuid::Uuid;
 
+/// 一个目标写入前后的完整内容
+struct PlannedWrite {
+    target: Target,
+    current: Vec<u8>,
+    content: Vec<u8>,
+}
+
+/// 生成目标写入后的内容并做写入前的安全检查，其他目标内容没有变化时返回 None，不需要写入
+fn plan_write(target: &Target, hosts_content: &str) -> Result<Option<PlannedWrite>> {
+    let content = generate_hosts_file_content(target, hosts_content)?;
+    let current = fs::read(&target.path)?;
+    if !target.is_system() && content == current {
+        return Ok(None);
+    }
+    check_before_write(&target.path, &content)?;
+    Ok(Some(PlannedWrite {
+        target: target.clone(),
+        current,
+        content,
+    }))
+}
+
+/// 写入一个目标，没有权限时返回 `no permission`
+fn write_target(target: &Target, content: Vec<u8>, password: Option<&str>) -> Result<()> {
+    replace_hosts_file_with_password(&target.path, password, content)
+        .map_err(|e| write_error(&target.name, e))
+}
+
+/// 写入中途失败时把已写入的目标恢复为原来的内容。全部恢复后返回原来的错误，
+/// 有目标无法恢复时返回 `partially written: 目标名称`
+fn rollback(
+    written: &[&PlannedWrite],
+    password: Option<&str>,
+    e: color_eyre::eyre::Error,
+) -> color_eyre::eyre::Error {
+    let failed = written
+        .iter()
+        .rev()
+        .filter(|plan| write_target(&plan.target, plan.current.clone(), password).is_err())
+        .map(|plan| plan.target.name.clone())
+        .collect::<Vec<_>>();
+    if failed.is_empty() {
+        return e;
+    }
+    error!("partially written: {failed:?}, caused by {e}");
+    color_eyre::eyre::Error::msg(format!("partially written: {}", failed.join(", ")))
+}
+
+/// 写入中途失败且无法恢复时，已经写入的目标名称
+pub fn partially_written(e: &color_eyre::eyre::Error) -> Option<String> {
+    e.to_string()
+        .strip_prefix("partially written: ")
+        .map(str::to_owned)
+}
+
+/// 移除目标中的受管理区块，没有区块时不写入，返回是否移除了区块
+pub fn remove_target_block(target: &Target, password: Option<&str>) -> Result<bool> {
+    let content = match generate_removed_content(target)? {
+        Some(content) => content,
+        None => return Ok(false),
+    };
+    write_target(target, content, password)?;
+    Ok(true)
+}
+
+/// 写入在编辑器中修改后的系统 hosts，受管理区块被修改时返回 `managed block changed`，写入前会自动备份
+pub fn write_edited_sys_hosts(text: &str, password: Option<&str>) -> Result<()> {
+    let content = generate_edited_content(&Target::system(), text)?;
+    write_target(&Target::system(), content, password)
+}
+
+/// 依次拼接各个 hosts 的内容，前后带有来源注释
+fn compose_hosts_content<'a>(items: impl IntoIterator<Item = &'a ConfigItem>) -> Result<String> {
+    let mut hosts_content = String::new();
+    for item in items {
+        let id = item.id();
+        let item_content = read_hosts_content(id)?;
+        hosts_content.push('\n');
+        hosts_content.push_str(&wrap_profile(item, read_hosts_modified(id)?, &item_content));
+    }
+    Ok(hosts_content)
+}
+
 pub struct HostsList {
     item_list: Vec<ConfigItem>,
     state: ListState,
     enabled_ids: Vec<String>,
     selected: Option<String>,
     event_subject: Option<Rc<RefCell<Subject>>>,
+    load_error: Option<String>,
 }
 
 impl HostsList {
@@ -32,6 +129,7 @@ impl HostsList {
             selected: None,
             state: ListState::default(),
             event_subject: None,
+            load_error: None,
         }
     }
 
@@ -46,8 +144,12 @@ impl HostsList {
 
     pub fn init(&mut self) {
         self.item_list.push(self.create_sys_item());
-        if let Ok(mut config_item_list) = read_config() {
-            self.item_list.append(&mut config_item_list);
+        match read_config() {
+            Ok(mut config_item_list) => self.item_list.append(&mut config_item_list),
+            Err(e) => {
+                error!("{e}");
+                self.load_error = Some(e.to_string());
+            }
         }
         self.selected = Some(self.item_list[0].id().to_owned());
         self.dispatch_subject();
@@ -80,6 +182,20 @@ impl HostsList {
                 .position(|item| item.id().to_owned() == id)
             {
                 self.item_list.remove(idx);
+                for group in self
+                    .item_list
+                    .iter_mut()
+                    .filter(|item| item.is_group() && item.members().contains(&id))
+                {
+                    let members = group
+                        .members()
+                        .iter()
+                        .filter(|member| **member != id)
+                        .cloned()
+                        .collect();
+                    group.set_members(members);
+                    save_config_item(group)?;
+                }
                 if id == self.get_selected_id().clone().unwrap_or("".to_owned()) {
                     if self.item_list.len() > 0 {
                         self.selected = Some(self.item_list[0].id().to_owned());
@@ -107,7 +223,7 @@ impl HostsList {
 
     pub fn toggle_on_off(
         &mut self,
-        password: Option<String>,
+        password: Option<&str>,
         only_update_content: bool,
     ) -> Result<()> {
         let id: String = self.selected.clone().unwrap_or("".to_owned());
@@ -117,25 +233,19 @@ impl HostsList {
         let config = find_config_by_id(&self.item_list, &id)
             .ok_or(color_eyre::eyre::Error::msg("not found config"))?;
         let on = config.is_on();
-        if only_update_content && !on {
+        if only_update_content && !self.is_applied(&id) {
             return Ok(());
         }
-        let hosts_content = if !only_update_content {
-            self.generate_hosts_content(&id, !on)?
-        } else {
-            self.generate_hosts_content(&id, true)?
-        };
-        if password.is_none() || cfg!(target_os = "windows") {
-            if write_sys_hosts(hosts_content.clone()).is_err() {
-                return Err(color_eyre::eyre::Error::msg("no permission"));
-            }
-        } else if write_sys_hosts_with_sudo(
-            password.clone().unwrap_or("".to_owned()),
-            hosts_content,
-        )
-        .is_err()
+        if (only_update_content || !on)
+            && read_settings().block_invalid_profiles
+            && !validate_hosts(&read_hosts_content(&id)?).is_empty()
         {
-            return Err(color_eyre::eyre::Error::msg("no permission"));
+            return Err(color_eyre::eyre::Error::msg("invalid hosts"));
+        }
+        if !only_update_content {
+            self.write_targets(&id, !on, password)?;
+        } else {
+            self.write_targets(&id, on, password)?;
         }
         if !only_update_content {
             let config_title = config.title().to_owned();
@@ -145,11 +255,305 @@ impl HostsList {
             )?;
             let config = find_mut_config_by_id(&mut self.item_list, &id).unwrap();
             config.set_is_on(!on);
+            if !on {
+                self.disable_exclusive_siblings(&id)?;
+            }
         }
         
         Ok(())
     }
 
+    /// 停用与 `id` 处于同一互斥集合中的其他 hosts，只更新配置
+    fn disable_exclusive_siblings(&mut self, id: &String) -> Result<()> {
+        let exclusive_set = find_config_by_id(&self.item_list, id)
+            .and_then(|item| item.exclusive_set())
+            .cloned();
+        if exclusive_set.is_none() {
+            return Ok(());
+        }
+        for item in self.item_list.iter_mut().filter(|item| {
+            item.id() != id && item.is_on() && item.exclusive_set() == exclusive_set.as_ref()
+        }) {
+            item.set_is_on(false);
+            update_config_item(item.id().clone(), item)?;
+        }
+        Ok(())
+    }
+
+    /// 设置当前选中 hosts 所属的互斥集合，集合中已有其他启用的 hosts 时不允许加入已启用的 hosts
+    pub fn set_exclusive_set(&mut self, exclusive_set: Option<String>) -> Result<()> {
+        let id = self.selected.clone().unwrap_or_default();
+        if id == "system" {
+            return Ok(());
+        }
+        let exclusive_set = exclusive_set.filter(|set| !set.trim().is_empty());
+        let item = find_config_by_id(&self.item_list, &id)
+            .ok_or(color_eyre::eyre::Error::msg("not found config"))?;
+        if item.is_on()
+            && exclusive_set.is_some()
+            && self.item_list.iter().any(|other| {
+                other.id() != &id
+                    && other.is_on()
+                    && other.exclusive_set() == exclusive_set.as_ref()
+            })
+        {
+            return Err(color_eyre::eyre::Error::msg("exclusive set conflict"));
+        }
+        let item = find_mut_config_by_id(&mut self.item_list, &id).unwrap();
+        item.set_exclusive_set(exclusive_set);
+        save_config_item(item)
+    }
+
+    /// 按当前的启用状态重新写入系统 hosts 和其他目标
+    pub fn apply_enabled(&self, password: Option<&str>) -> Result<()> {
+        self.write_targets(&String::new(), false, password)
+    }
+
+    /// 先生成并检查所有目标的内容，全部通过后才依次写入系统 hosts 和设置中的其他目标，
+    /// 写入中途失败时恢复已写入的目标。没有 hosts 使用的目标无法读取时（如容器未启动）跳过
+    fn write_targets(
+        &self,
+        toggled_id: &String,
+        toggled: bool,
+        password: Option<&str>,
+    ) -> Result<()> {
+        let mut planned = vec![];
+        for target in get_targets() {
+            let hosts_content = self.generate_target_content(&target.name, toggled_id, toggled)?;
+            let used = target.is_system()
+                || self
+                    .item_list
+                    .iter()
+                    .any(|item| item.applies_to(&target.name));
+            match plan_write(&target, &hosts_content) {
+                Ok(plan) => planned.extend(plan),
+                Err(e) if !used => debug!("skip target {}: {e}", target.name),
+                Err(e) => return Err(e),
+            }
+        }
+        let mut written = vec![];
+        for plan in &planned {
+            if let Err(e) = write_target(&plan.target, plan.content.clone(), password) {
+                return Err(rollback(&written, password, e));
+            }
+            written.push(plan);
+        }
+        Ok(())
+    }
+
+    /// 系统 hosts 中受管理区块的内容与按配置生成的内容不一致时（如被手动修改），
+    /// 返回 (按配置生成的内容, 实际内容)，比较时忽略来源注释、空行和行尾空白
+    pub fn check_managed_block(&self) -> Result<Option<(String, String)>> {
+        let sys_hosts = read_sys_hosts()?;
+        let live = extract_managed_block(&sys_hosts, &Target::system().block_markers()?)
+            .unwrap_or_default();
+        let expected = self.generate_hosts_content(&String::new(), false)?;
+        if normalize_block(&strip_provenance(live)) == normalize_block(&strip_provenance(&expected))
+        {
+            Ok(None)
+        } else {
+            Ok(Some((expected, live.to_owned())))
+        }
+    }
+
+    /// 停用所有 hosts，只更新配置，需要调用方重新写入
+    pub fn disable_all(&mut self) -> Result<()> {
+        for item in self.item_list.iter_mut().filter(|item| item.is_on()) {
+            if item.id() == "system" {
+                continue;
+            }
+            item.set_is_on(false);
+            update_config_item(item.id().clone(), item)?;
+        }
+        Ok(())
+    }
+
+    /// 把系统 hosts 中的实际内容去掉来源注释后导入为新的 hosts 并只启用它，
+    /// 需要调用方重新写入以更新来源注释
+    pub fn adopt_managed_block(&mut self, title: String, content: String) -> Result<String> {
+        self.disable_all()?;
+        let id = Uuid::new_v4().to_string();
+        add_item(
+            id.clone(),
+            title.clone(),
+            normalize_block(&strip_provenance(&content)).join("\n"),
+        )?;
+        let item = ConfigItem::new(id.clone(), true, title, ConfigItemType::User);
+        save_config_item(&item)?;
+        self.item_list.push(item);
+        self.set_selected_item(id.clone());
+        Ok(id)
+    }
+
+    /// 用系统 hosts 中受管理区块以外选中的行新建 hosts 并启用，同时从原位置删除这些行，返回它的 id。
+    /// 写入失败（包括没有权限）时删除新建的 hosts，不会留下孤立的 hosts，输入密码后重试也不会重复
+    pub fn adopt_lines(
+        &mut self,
+        title: String,
+        lines: &[(usize, String)],
+        password: Option<&str>,
+    ) -> Result<String> {
+        let id = self.add_adopted_item(title, lines)?;
+        if let Err(e) = self.write_adopted_lines(&id, lines, password) {
+            if let Err(e) = self.delete_item(id) {
+                error!("{e}");
+            }
+            return Err(e);
+        }
+        let item = find_mut_config_by_id(&mut self.item_list, &id)
+            .ok_or(color_eyre::eyre::Error::msg("not found config"))?;
+        item.set_is_on(true);
+        update_config_item(id.clone(), item)?;
+        Ok(id)
+    }
+
+    /// 用选中的行新建一个停用的 hosts，返回它的 id
+    fn add_adopted_item(&mut self, title: String, lines: &[(usize, String)]) -> Result<String> {
+        let id = Uuid::new_v4().to_string();
+        let content = lines
+            .iter()
+            .map(|(_, line)| line.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        add_item(id.clone(), title.clone(), content)?;
+        self.item_list.push(ConfigItem::new(
+            id.clone(),
+            false,
+            title,
+            ConfigItemType::User,
+        ));
+        Ok(id)
+    }
+
+    /// 按启用导入的 hosts 后的内容写入系统 hosts，同时从受管理区块以外删除导入的行，系统 hosts 只写入一次
+    fn write_adopted_lines(
+        &self,
+        id: &String,
+        lines: &[(usize, String)],
+        password: Option<&str>,
+    ) -> Result<()> {
+        let hosts_content = self.generate_hosts_content(id, true)?;
+        let content = generate_adopted_content(&Target::system(), lines, &hosts_content)?;
+        write_target(&Target::system(), content, password)
+    }
+
+    /// 设置当前选中 hosts 写入的目标，已启用时需要调用方重新写入
+    pub fn set_targets(&mut self, targets: Vec<String>) -> Result<()> {
+        let id = self.selected.clone().unwrap_or_default();
+        if id == "system" {
+            return Ok(());
+        }
+        let item = find_mut_config_by_id(&mut self.item_list, &id)
+            .ok_or(color_eyre::eyre::Error::msg("not found config"))?;
+        item.set_targets(targets);
+        save_config_item(item)
+    }
+
+    pub fn add_remote_item(
+        &mut self,
+        title: String,
+        url: String,
+        refresh_interval: u64,
+    ) -> Result<String> {
+        let id = Uuid::new_v4().to_string();
+        let item = ConfigItem::new_remote(
+            id.clone(),
+            title.clone(),
+            RemoteSource {
+                url,
+                refresh_interval,
+                ..Default::default()
+            },
+        );
+        add_item(id.clone(), title, "".to_owned())?;
+        save_config_item(&item)?;
+        self.item_list.push(item);
+        Ok(id)
+    }
+
+    pub fn get_remote_items(&self) -> Vec<&ConfigItem> {
+        self.item_list
+            .iter()
+            .filter(|item| item.remote().is_some())
+            .collect()
+    }
+
+    /// 保存远程 hosts 的拉取结果，已启用且内容有变化时重新写入系统 hosts
+    pub fn apply_remote_result(
+        &mut self,
+        id: &String,
+        res: Result<FetchResult>,
+        password: Option<&str>,
+    ) -> Result<()> {
+        let item = find_mut_config_by_id(&mut self.item_list, id)
+            .ok_or(color_eyre::eyre::Error::msg("not found config"))?;
+        let remote = item
+            .remote_mut()
+            .ok_or(color_eyre::eyre::Error::msg("not remote config"))?;
+        let failed = res.is_err();
+        let content = apply_fetch_result(remote, res);
+        let changed = match content {
+            Some(content) if content != read_item_data(id)? => {
+                write_item_data(id, content)?;
+                true
+            }
+            _ => false,
+        };
+        save_config_item(item)?;
+        if changed && self.selected.as_ref() == Some(id) {
+            self.dispatch_subject();
+        }
+        if changed && self.is_applied(id) {
+            self.apply_enabled(password)?;
+        }
+        if failed {
+            return Err(color_eyre::eyre::Error::msg("fetch failed"));
+        }
+        Ok(())
+    }
+
+    pub fn add_group_item(&mut self, title: String, members: Vec<String>) -> Result<String> {
+        let id = Uuid::new_v4().to_string();
+        let item = ConfigItem::new_group(id.clone(), title.clone(), members);
+        add_item(id.clone(), title, "".to_owned())?;
+        save_config_item(&item)?;
+        self.item_list.push(item);
+        Ok(id)
+    }
+
+    /// 修改分组的成员，分组已启用时需要调用方重新写入系统 hosts
+    pub fn update_group_members(&mut self, id: &String, members: Vec<String>) -> Result<()> {
+        let item = find_mut_config_by_id(&mut self.item_list, id)
+            .filter(|item| item.is_group())
+            .ok_or(color_eyre::eyre::Error::msg("not found config"))?;
+        item.set_members(members);
+        save_config_item(item)?;
+        if self.selected.as_ref() == Some(id) {
+            self.dispatch_subject();
+        }
+        Ok(())
+    }
+
+    /// 可以加入分组的 hosts
+    pub fn get_member_candidates(&self) -> Vec<&ConfigItem> {
+        self.item_list
+            .iter()
+            .filter(|item| item.id() != "system" && !item.is_group())
+            .collect()
+    }
+
+    /// hosts 自身已启用，或者被某个已启用的分组包含
+    pub fn is_applied(&self, id: &String) -> bool {
+        self.item_list.iter().any(|item| {
+            item.is_on() && (item.id() == id || (item.is_group() && item.members().contains(id)))
+        })
+    }
+
+    /// 读取配置失败的原因，此时配置不会被覆盖
+    pub fn load_error(&self) -> Option<&String> {
+        self.load_error.as_ref()
+    }
+
     pub fn get_selected_id(&self) -> &Option<String> {
         &self.selected
     }
@@ -263,9 +667,12 @@ impl HostsList {
         self.sync_config();
     }
 
-    pub fn generate_hosts_content(&self, toggled_id: &String, toggled: bool) -> Result<String> {
-        let enabled = self
-            .item_list
+    /// 将 `toggled_id` 的状态视为 `toggled` 后，所有启用的 hosts。启用时同一互斥集合中的其他 hosts 视为停用
+    pub fn enabled_items(&self, toggled_id: &String, toggled: bool) -> Vec<&ConfigItem> {
+        let exclusive_set = find_config_by_id(&self.item_list, toggled_id)
+            .filter(|_| toggled)
+            .and_then(|item| item.exclusive_set());
+        self.item_list
             .iter()
             .filter(|item| {
                 if item.id() == "system" {
@@ -274,17 +681,106 @@ impl HostsList {
                 if item.id() == toggled_id {
                     return toggled;
                 }
+                if exclusive_set.is_some() && item.exclusive_set() == exclusive_set {
+                    return false;
+                }
                 item.is_on()
             })
-            .collect::<Vec<_>>();
-        let mut hosts_content = String::new();
-        for item in enabled {
-            let id = item.id();
-            let item_content = read_item_data(id)?;
-            hosts_content.push_str("\n");
-            hosts_content.push_str(&item_content);
+            .collect::<Vec<_>>()
+    }
+
+    pub fn generate_hosts_content(&self, toggled_id: &String, toggled: bool) -> Result<String> {
+        self.generate_target_content(SYSTEM_TARGET, toggled_id, toggled)
+    }
+
+    /// 写入目标 `target` 的受管理区块的内容，每个 hosts 的内容前后带有来源注释
+    pub fn generate_target_content(
+        &self,
+        target: &str,
+        toggled_id: &String,
+        toggled: bool,
+    ) -> Result<String> {
+        let enabled = self.enabled_items(toggled_id, toggled);
+        compose_hosts_content(enabled.into_iter().filter(|item| item.applies_to(target)))
+    }
+
+    /// 按 `ids` 的顺序组合 hosts，不考虑启用状态和写入目标
+    pub fn generate_selected_content(&self, ids: &[String]) -> Result<String> {
+        let items = ids
+            .iter()
+            .map(|id| {
+                find_config_by_id(&self.item_list, id)
+                    .filter(|item| item.id() != "system")
+                    .ok_or(color_eyre::eyre::Error::msg("not found config"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        compose_hosts_content(items)
+    }
+
+    /// 导出组合后的 hosts，`ids` 为 None 时使用已启用的 hosts。
+    /// `full` 时导出写入后的完整系统 hosts（不会写入），否则只导出受管理区块的内容。
+    /// 来源注释中带有文件的修改时间，默认去掉，相同的 hosts 每次导出的内容都相同
+    pub fn export_content(
+        &self,
+        ids: Option<&[String]>,
+        full: bool,
+        provenance: bool,
+    ) -> Result<Vec<u8>> {
+        let mut hosts_content = match ids {
+            Some(ids) => self.generate_selected_content(ids)?,
+            None => self.generate_hosts_content(&String::new(), false)?,
+        };
+        if !provenance {
+            hosts_content = format!("\n{}", strip_provenance(&hosts_content));
+        }
+        if full {
+            return generate_sys_hosts_content(hosts_content);
+        }
+        let hosts_content = hosts_content.trim_start_matches('\n');
+        if hosts_content.is_empty() {
+            return Ok(vec![]);
+        }
+        Ok(format!("{}\n", hosts_content).into_bytes())
+    }
+
+    /// 切换当前选中的 hosts 后系统 hosts 的变化，返回（当前内容，切换后的内容）
+    pub fn preview_toggle(&self) -> Result<(String, String)> {
+        let current = read_sys_hosts()?;
+        match self.get_selected_item() {
+            Some(item) if item.id() != "system" => {
+                let hosts_content = self.generate_hosts_content(item.id(), !item.is_on())?;
+                let content = generate_sys_hosts_content(hosts_content)?;
+                Ok((current, String::from_utf8_lossy(&content).into_owned()))
+            }
+            _ => Ok((current.clone(), current)),
+        }
+    }
+
+    /// 检测启用的 hosts 之间，以及与系统 hosts 中未受管理部分之间的主机名冲突
+    pub fn check_conflicts(&self, toggled_id: &String, toggled: bool) -> Result<Vec<Conflict>> {
+        let mut sources = vec![HostsSource::new(
+            "system",
+            "system",
+            read_unmanaged_sys_hosts()?,
+        )];
+        for item in self.enabled_items(toggled_id, toggled) {
+            sources.push(HostsSource::new(
+                item.id(),
+                item.title(),
+                read_hosts_content(item.id())?,
+            ));
+        }
+        Ok(find_conflicts(&sources, "system"))
+    }
+
+    /// 启用当前选中的 hosts（或更新已启用 hosts 的内容）后会产生的冲突，停用时不检测
+    pub fn check_selected_conflicts(&self, only_update_content: bool) -> Result<Vec<Conflict>> {
+        match self.get_selected_item() {
+            Some(item) if item.id() != "system" && item.is_on() == only_update_content => {
+                self.check_conflicts(item.id(), true)
+            }
+            _ => Ok(vec![]),
         }
-        Ok(hosts_content)
     }
 
     pub fn inject_subject(&mut self, subject: Rc<RefCell<Subject>>) {
@@ -311,9 +807,15 @@ impl HostsList {
     pub fn draw(&mut self, area: Rect, buf: &mut Buffer) {
         let block = Block::new();
         block.render(area, buf);
-        let block = Block::bordered()
+        let mut block = Block::bordered()
             .style(Style::new().white().on_black().bold())
             .title("Hosts List");
+        if self.load_error.is_some() {
+            block = block.title_bottom(Line::styled(
+                " 配置文件无法读取，修改不会保存 ",
+                Style::new().light_red(),
+            ));
+        }
         let items: Vec<ListItem> = self
             .item_list
             .iter()
@@ -327,3 +829,99 @@ impl HostsList {
         StatefulWidget::render(list, area, buf, &mut self.state);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::read_config;
+    use crate::paths::with_temp_data_dir;
+    use crate::settings::{write_settings, Settings};
+
+    #[test]
+    fn test_rollback() -> Result<()> {
+        with_temp_data_dir(|dir| {
+            let original = b"127.0.0.1 localhost\n".to_vec();
+            let path = dir.join("hosts");
+            fs::write(&path, &original)?;
+            let target = Target {
+                name: "docker".to_owned(),
+                path: path.to_string_lossy().into_owned(),
+                ..Target::default()
+            };
+            let plan = plan_write(&target, "10.0.0.1 a.test")?.unwrap();
+            assert_eq!(plan.current, original);
+            write_target(&target, plan.content.clone(), None)?;
+            assert_ne!(fs::read(&path)?, original);
+
+            // 已写入的目标恢复后返回原来的错误
+            let e = rollback(
+                &[&plan],
+                None,
+                color_eyre::eyre::Error::msg("no permission"),
+            );
+            assert_eq!(e.to_string(), "no permission");
+            assert_eq!(fs::read(&path)?, original);
+            assert_eq!(partially_written(&e), None);
+
+            // 无法恢复的目标会在错误中列出
+            let missing = PlannedWrite {
+                target: Target {
+                    name: "vm".to_owned(),
+                    path: dir.join("missing/hosts").to_string_lossy().into_owned(),
+                    ..Target::default()
+                },
+                current: original.clone(),
+                content: original.clone(),
+            };
+            let e = rollback(
+                &[&plan, &missing],
+                None,
+                color_eyre::eyre::Error::msg("no permission"),
+            );
+            assert_eq!(partially_written(&e), Some("vm".to_owned()));
+
+            // 无法读取的目标在写入前就返回错误
+            assert!(plan_write(&missing.target, "10.0.0.1 a.test").is_err());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_adopt_lines() -> Result<()> {
+        with_temp_data_dir(|dir| {
+            let hosts = dir.join("hosts");
+            write_settings(&Settings {
+                hosts_file: Some(hosts.to_string_lossy().into_owned()),
+                ..Settings::default()
+            })?;
+            let original = "127.0.0.1 localhost\n10.0.0.1 a.test\n";
+            fs::write(&hosts, original)?;
+            let mut hosts_list = HostsList::new();
+            hosts_list.init();
+
+            // 写入失败时不会留下新建的 hosts
+            let stale = vec![(1, "10.0.0.9 stale.test".to_owned())];
+            let e = hosts_list
+                .adopt_lines("导入的 hosts".to_owned(), &stale, None)
+                .unwrap_err();
+            assert_eq!(e.to_string(), "hosts changed");
+            assert_eq!(hosts_list.get_all_hosts_item_list().len(), 1);
+            assert!(read_config()?.is_empty());
+            assert_eq!(fs::read_dir(dir.join("data"))?.count(), 0);
+            assert_eq!(fs::read_to_string(&hosts)?, original);
+
+            let lines = vec![(1, "10.0.0.1 a.test".to_owned())];
+            let id = hosts_list.adopt_lines("导入的 hosts".to_owned(), &lines, None)?;
+            assert!(hosts_list.is_applied(&id));
+            assert_eq!(read_config()?.len(), 1);
+            let content = fs::read_to_string(&hosts)?;
+            // 导入的行从受管理区块以外移到了区块中
+            let (unmanaged, block) = content
+                .split_once("# --- SWITCHHOSTS_RS_CONTENT_START ---")
+                .unwrap();
+            assert_eq!(unmanaged.trim_end(), "127.0.0.1 localhost");
+            assert!(block.contains("\n10.0.0.1 a.test\n"));
+            Ok(())
+        })
+    }
+}
@@ -1,4 +1,6 @@
+use crate::data::get_switch_hosts_rs_dir;
 use crate::util::Result;
+use color_eyre::eyre::Error;
 use log::LevelFilter;
 use log4rs::{
     append::rolling_file::policy::compound::{
@@ -7,33 +9,29 @@ use log4rs::{
     config::{Appender, Config, Root},
     encode::pattern::PatternEncoder,
 };
-use std::env;
 
 const TRIGGER_FILE_SIZE: u64 = 2 * 1024 * 1024;
 
 const LOG_FILE_COUNT: u32 = 10;
 
+/// 日志写在数据目录中，不会留在运行时的当前目录
+///
+/// 没有 HOME 也没有 XDG 目录、无法确定数据目录时不写日志文件
 pub fn init_logger() -> Result<()> {
+    let Some(log_dir) = get_switch_hosts_rs_dir() else {
+        return Ok(());
+    };
     let trigger = SizeTrigger::new(TRIGGER_FILE_SIZE);
-    let archive_path = env::current_dir()
-        .unwrap()
-        .join("debug.{}.log")
-        .to_string_lossy()
-        .into_owned();
+    let archive_path = log_dir.join("debug.{}.log").to_string_lossy().into_owned();
     let roller = FixedWindowRoller::builder()
         .base(0)
         .build(&archive_path, LOG_FILE_COUNT)
-        .unwrap();
+        .map_err(Error::msg)?;
     let policy = CompoundPolicy::new(Box::new(trigger), Box::new(roller));
-    let log_file_path = env::current_dir()
-        .unwrap()
-        .join("debug.log")
-        .to_string_lossy()
-        .into_owned();
+    let log_file_path = log_dir.join("debug.log").to_string_lossy().into_owned();
     let logfile = log4rs::append::rolling_file::RollingFileAppender::builder()
         .encoder(Box::new(PatternEncoder::new("{l} - {m} {d}\n")))
-        .build(&log_file_path, Box::new(policy))
-        .unwrap();
+        .build(&log_file_path, Box::new(policy))?;
 
     let config = Config::builder()
         .appender(Appender::builder().build("logfile", Box::new(logfile)))
@@ -41,8 +39,7 @@ pub fn init_logger() -> Result<()> {
             Root::builder()
                 .appender("logfile")
                 .build(LevelFilter::Debug),
-        )
-        .unwrap();
+        )?;
 
     let _handle = log4rs::init_config(config)?;
 
@@ -54,12 +51,16 @@ mod tests {
     use log::{debug, error};
 
     use super::init_logger;
+    use crate::paths::with_temp_data_dir;
 
     #[test]
     fn test_debug_log() {
-        init_logger();
-        debug!("test");
+        with_temp_data_dir(|dir| {
+            init_logger();
+            debug!("test");
 
-        error!("error");
+            error!("error");
+            assert!(dir.join("debug.log").exists());
+        });
     }
 }
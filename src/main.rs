@@ -2,7 +2,7 @@
 #![allow(unused_variables)]
 #![allow(unused_must_use)]
 
-use std::{io, panic};
+use std::{env, io, panic, process};
 
 use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
 use ratatui::DefaultTerminal;
@@ -27,6 +27,8 @@ mod util;
 
 mod password_input;
 
+mod password_cache;
+
 mod single_line_textarea;
 
 mod observer;
@@ -37,6 +39,50 @@ mod search;
 
 mod search_result;
 
+mod cli;
+
+mod hosts_parser;
+
+mod validator;
+
+mod settings;
+
+mod conflict;
+
+mod conflict_dialog;
+
+mod reconcile_dialog;
+
+mod diff;
+
+mod preview;
+
+mod backup;
+
+mod backup_list;
+
+mod remote;
+
+mod url_input;
+
+mod member_picker;
+
+mod exclusive_set_input;
+
+mod atomic_write;
+
+mod privileged;
+
+mod paths;
+
+mod target;
+
+mod text_format;
+
+mod provenance;
+
+mod export_input;
+
 use app::App;
 
 fn init_hooks() -> color_eyre::Result<()> {
@@ -64,8 +110,22 @@ fn restore_terminal() -> color_eyre::Result<()> {
 }
 
 fn main() -> color_eyre::Result<()> {
+    let args = match cli::take_path_options(env::args().skip(1).collect()) {
+        Ok((overrides, args)) => {
+            paths::set_overrides(overrides);
+            // 数据目录可能由 --data-dir 指定，日志在此之后初始化
+            logger::init_logger();
+            args
+        }
+        Err(message) => {
+            eprintln!("{}", message);
+            process::exit(cli::EXIT_USAGE);
+        }
+    };
+    if !args.is_empty() {
+        process::exit(cli::run(args));
+    }
     init_hooks()?;
-    logger::init_logger();
     let mut terminal = init_terminal()?;
     let result = App::new().run(&mut terminal);
     restore_terminal()?;
@@ -0,0 +1,146 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, StatefulWidget},
+};
+
+use crate::data::ConfigItem;
+
+#[derive(Debug, Default)]
+struct Candidate {
+    id: String,
+    title: String,
+    checked: bool,
+}
+
+/// 选择分组的成员，勾选的成员按列表中的顺序合并。也用来选择 hosts 写入的目标
+#[derive(Debug, Default)]
+pub struct MemberPicker {
+    title: String,
+    candidates: Vec<Candidate>,
+    state: ListState,
+}
+
+impl MemberPicker {
+    pub fn new() -> Self {
+        MemberPicker::default()
+    }
+
+    pub fn set_candidates(&mut self, items: &[&ConfigItem], members: &[String]) {
+        let options = items
+            .iter()
+            .map(|item| (item.id().to_owned(), item.title().to_owned()))
+            .collect::<Vec<_>>();
+        self.set_options("分组成员", &options, members);
+    }
+
+    /// `options` 为 (id, 显示的名称)，已选的按原来的顺序排在前面
+    pub fn set_options(&mut self, title: &str, options: &[(String, String)], checked: &[String]) {
+        let mut candidates = checked
+            .iter()
+            .filter_map(|id| options.iter().find(|(option, _)| option == id))
+            .map(|(id, title)| Candidate {
+                id: id.clone(),
+                title: title.clone(),
+                checked: true,
+            })
+            .collect::<Vec<_>>();
+        for (id, title) in options.iter().filter(|(id, _)| !checked.contains(id)) {
+            candidates.push(Candidate {
+                id: id.clone(),
+                title: title.clone(),
+                checked: false,
+            });
+        }
+        self.title = title.to_owned();
+        self.candidates = candidates;
+        self.state.select(if self.candidates.is_empty() {
+            None
+        } else {
+            Some(0)
+        });
+    }
+
+    pub fn get_members(&self) -> Vec<String> {
+        self.candidates
+            .iter()
+            .filter(|candidate| candidate.checked)
+            .map(|candidate| candidate.id.clone())
+            .collect()
+    }
+
+    /// 返回 Some(true) 表示确认，Some(false) 表示取消
+    pub fn handle_event(&mut self, event: KeyEvent) -> Option<bool> {
+        let selected = self.state.selected();
+        match (event.modifiers, event.code) {
+            (_, KeyCode::Enter) => Some(true),
+            (_, KeyCode::Esc) => Some(false),
+            (_, KeyCode::Char(' ')) => {
+                if let Some(candidate) = selected.and_then(|idx| self.candidates.get_mut(idx)) {
+                    candidate.checked = !candidate.checked;
+                }
+                None
+            }
+            (KeyModifiers::SHIFT, KeyCode::Up) => {
+                if let Some(idx) = selected.filter(|idx| *idx > 0) {
+                    self.candidates.swap(idx, idx - 1);
+                    self.state.select(Some(idx - 1));
+                }
+                None
+            }
+            (KeyModifiers::SHIFT, KeyCode::Down) => {
+                if let Some(idx) = selected.filter(|idx| idx + 1 < self.candidates.len()) {
+                    self.candidates.swap(idx, idx + 1);
+                    self.state.select(Some(idx + 1));
+                }
+                None
+            }
+            (_, KeyCode::Up) => {
+                self.state.select_previous();
+                None
+            }
+            (_, KeyCode::Down) => {
+                if selected.unwrap_or(0) + 1 < self.candidates.len() {
+                    self.state.select_next();
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    pub fn draw(&mut self, area: Rect, buf: &mut Buffer) {
+        let strong_style = Style::default()
+            .add_modifier(Modifier::BOLD)
+            .fg(Color::LightGreen);
+        let footer = Line::from(vec![
+            Span::styled(" Space", strong_style),
+            Span::raw(" 选择 "),
+            Span::styled("Shift+↑↓", strong_style),
+            Span::raw(" 调整顺序 "),
+            Span::styled("Enter", strong_style),
+            Span::raw(" 确认 "),
+            Span::styled("Esc", strong_style),
+            Span::raw(" 取消 "),
+        ]);
+        let block = Block::bordered()
+            .style(Style::new().white().on_black())
+            .title(self.title.as_str())
+            .title_bottom(footer);
+        let items = self
+            .candidates
+            .iter()
+            .map(|candidate| {
+                let mark = if candidate.checked { "[x]" } else { "[ ]" };
+                ListItem::new(format!("{} {}", mark, candidate.title))
+            })
+            .collect::<Vec<_>>();
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().bg(Color::DarkGray));
+        StatefulWidget::render(list, area, buf, &mut self.state);
+    }
+}
@@ -0,0 +1,101 @@
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use zeroize::Zeroizing;
+
+/// sudo 密码，drop 时清零内存，Debug 不会输出内容
+#[derive(Clone)]
+pub struct SecretPassword(Zeroizing<String>);
+
+impl SecretPassword {
+    pub fn new(password: String) -> Self {
+        SecretPassword(Zeroizing::new(password))
+    }
+
+    /// 直接接管已经会清零的字符串，不产生未清零的副本
+    pub fn from_zeroizing(password: Zeroizing<String>) -> Self {
+        SecretPassword(password)
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for SecretPassword {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretPassword(***)")
+    }
+}
+
+/// 缓存输入过的 sudo 密码，超过空闲时间没有使用就自动清除
+#[derive(Debug)]
+pub struct PasswordCache {
+    password: Option<SecretPassword>,
+    last_used: Instant,
+    idle_timeout: Duration,
+}
+
+impl PasswordCache {
+    pub fn new(idle_timeout: Duration) -> Self {
+        PasswordCache {
+            password: None,
+            last_used: Instant::now(),
+            idle_timeout,
+        }
+    }
+
+    pub fn set(&mut self, password: SecretPassword) {
+        self.password = Some(password);
+        self.last_used = Instant::now();
+    }
+
+    /// 取出缓存的密码并刷新空闲时间，已过期时返回 None
+    pub fn get(&mut self) -> Option<&SecretPassword> {
+        self.expire_idle();
+        if self.password.is_some() {
+            self.last_used = Instant::now();
+        }
+        self.password.as_ref()
+    }
+
+    /// 立即清除缓存的密码，返回之前是否缓存了密码
+    pub fn forget(&mut self) -> bool {
+        self.password.take().is_some()
+    }
+
+    /// 超过空闲时间时清除缓存的密码，返回是否清除了密码
+    pub fn expire_idle(&mut self) -> bool {
+        if self.password.is_some() && self.last_used.elapsed() >= self.idle_timeout {
+            return self.forget();
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_password_cache() {
+        let password = SecretPassword::new(String::from("secret"));
+        assert_eq!(format!("{:?}", password), "SecretPassword(***)");
+
+        let mut cache = PasswordCache::new(Duration::from_secs(60));
+        assert!(cache.get().is_none());
+        cache.set(password.clone());
+        assert_eq!(cache.get().map(SecretPassword::expose), Some("secret"));
+        assert!(!cache.expire_idle());
+        assert!(!format!("{:?}", cache).contains("secret"));
+        assert!(cache.forget());
+        assert!(cache.get().is_none());
+
+        let mut cache = PasswordCache::new(Duration::ZERO);
+        cache.set(password);
+        assert!(cache.expire_idle());
+        assert!(cache.get().is_none());
+    }
+}
@@ -1,15 +1,23 @@
 use crate::{
     hosts::check_password_correct,
+    password_cache::SecretPassword,
     single_line_textarea::{
         create_new_single_line_textarea, SingleLineTextarea, SinglelineTextareaType,
     },
 };
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{buffer::Buffer, layout::Rect};
+use std::time::{Duration, Instant};
+
+/// 连续输错密码的次数达到上限后，需要等待一段时间才能再次尝试
+const MAX_PASSWORD_ATTEMPTS: u32 = 3;
+const PASSWORD_LOCKOUT: Duration = Duration::from_secs(30);
 
 #[derive(Debug, Default)]
 pub struct PasswordInput<'a> {
     textarea: SingleLineTextarea<'a>,
+    failed_attempts: u32,
+    locked_until: Option<Instant>,
 }
 
 impl<'a> PasswordInput<'a> {
@@ -20,23 +28,73 @@ impl<'a> PasswordInput<'a> {
                 "请输入你的登录密码（sudo 密码）",
                 SinglelineTextareaType::Password,
             ),
+            failed_attempts: 0,
+            locked_until: None,
+        }
+    }
+
+    fn lockout_remaining(&mut self) -> Option<Duration> {
+        let remaining = self
+            .locked_until
+            .map(|until| until.saturating_duration_since(Instant::now()))
+            .filter(|remaining| !remaining.is_zero());
+        if remaining.is_none() && self.locked_until.take().is_some() {
+            self.failed_attempts = 0;
         }
+        remaining
+    }
+
+    fn check_password(&mut self, text: &str) -> bool {
+        if let Some(remaining) = self.lockout_remaining() {
+            self.textarea.set_error(format!(
+                "密码错误次数过多，请 {} 秒后再试",
+                remaining.as_secs() + 1
+            ));
+            return false;
+        }
+        let e = match check_password_correct(text) {
+            Ok(_) => {
+                self.failed_attempts = 0;
+                return true;
+            }
+            Err(e) => e,
+        };
+        let message = match e.to_string().as_str() {
+            "wrong password" => {
+                self.failed_attempts += 1;
+                if self.failed_attempts >= MAX_PASSWORD_ATTEMPTS {
+                    self.locked_until = Some(Instant::now() + PASSWORD_LOCKOUT);
+                    format!(
+                        "密码错误次数过多，请 {} 秒后再试",
+                        PASSWORD_LOCKOUT.as_secs()
+                    )
+                } else {
+                    format!(
+                        "密码错误，请重新输入（还可尝试 {} 次）",
+                        MAX_PASSWORD_ATTEMPTS - self.failed_attempts
+                    )
+                }
+            }
+            "not in sudoers" => "当前用户不在 sudoers 中，无法通过 sudo 写入".to_owned(),
+            "sudo missing" => "未找到 sudo 命令，请在设置中更换提权方式".to_owned(),
+            _ => format!("无法验证密码: {e}"),
+        };
+        self.textarea.set_error(message);
+        false
     }
 
-    pub fn handle_event(&mut self, event: KeyEvent) -> (bool, Option<String>) {
+    pub fn handle_event(&mut self, event: KeyEvent) -> (bool, Option<SecretPassword>) {
         match event.code {
             KeyCode::Esc => {
-                self.textarea.set_text("");
+                self.textarea.take_secret();
                 return (true, None);
             }
             KeyCode::Enter => {
-                let text: String = self.textarea.get_text();
-                let is_correct = check_password_correct(text.clone(), || {});
-                if is_correct.is_ok() {
-                    self.textarea.set_text("text");
-                    return (true, Some(String::from(text)));
+                // 取出后输入框即被清空，密码错误时需要重新输入
+                let text = SecretPassword::from_zeroizing(self.textarea.take_secret());
+                if self.check_password(text.expose()) {
+                    return (true, Some(text));
                 } else {
-                    self.textarea.set_error("密码错误，请重新输入");
                     return (false, None);
                 }
             }
@@ -0,0 +1,181 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+/// 指定数据目录的环境变量
+pub const DATA_DIR_ENV: &str = "SWITCHHOSTS_RS_DATA_DIR";
+
+/// 指定系统 hosts 路径的环境变量
+pub const HOSTS_FILE_ENV: &str = "SWITCHHOSTS_RS_HOSTS_FILE";
+
+const LEGACY_DIR_NAME: &str = ".SwitchHostsRs";
+
+const XDG_DIR_NAME: &str = "switchhosts-rs";
+
+/// 命令行参数指定的路径，优先级高于环境变量和设置
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PathOverrides {
+    pub data_dir: Option<PathBuf>,
+    pub hosts_file: Option<PathBuf>,
+}
+
+static OVERRIDES: RwLock<PathOverrides> = RwLock::new(PathOverrides {
+    data_dir: None,
+    hosts_file: None,
+});
+
+pub fn set_overrides(overrides: PathOverrides) {
+    *OVERRIDES.write().unwrap() = overrides;
+}
+
+fn get_overrides() -> PathOverrides {
+    OVERRIDES.read().unwrap().clone()
+}
+
+fn env_path(name: &str) -> Option<PathBuf> {
+    env::var_os(name)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// 命令行参数或环境变量显式指定的数据目录
+fn explicit_data_dir() -> Option<PathBuf> {
+    get_overrides().data_dir.or_else(|| env_path(DATA_DIR_ENV))
+}
+
+/// 没有指定数据目录时，已经存在的 `~/.SwitchHostsRs` 优先，其次是 `$XDG_DATA_HOME/switchhosts-rs`
+fn default_data_dir(home: Option<&Path>, xdg_data_home: Option<&Path>) -> Option<PathBuf> {
+    let legacy = home.map(|home| home.join(LEGACY_DIR_NAME));
+    match (&legacy, xdg_data_home) {
+        (Some(legacy), _) if legacy.exists() => Some(legacy.clone()),
+        (_, Some(xdg_data_home)) => Some(xdg_data_home.join(XDG_DIR_NAME)),
+        _ => legacy,
+    }
+}
+
+/// 存在的 `$XDG_CONFIG_HOME/switchhosts-rs/settings.json`
+fn xdg_settings_path() -> Option<PathBuf> {
+    env_path("XDG_CONFIG_HOME")
+        .map(|dir| dir.join(XDG_DIR_NAME).join("settings.json"))
+        .filter(|path| path.exists())
+}
+
+/// 设置文件中的 `data_dir`。这里只读取这一项，其余的设置在确定数据目录后由 settings 模块读取
+fn configured_data_dir(settings_path: &Path) -> Option<PathBuf> {
+    let content = fs::read_to_string(settings_path).ok()?;
+    let value = serde_json::from_str::<serde_json::Value>(&content).ok()?;
+    value
+        .get("data_dir")?
+        .as_str()
+        .filter(|dir| !dir.trim().is_empty())
+        .map(PathBuf::from)
+}
+
+/// 依次使用命令行参数、环境变量、`$XDG_CONFIG_HOME/switchhosts-rs/settings.json` 中的 `data_dir`
+/// 指定的数据目录，都没有时使用默认的数据目录
+pub fn resolve_data_dir() -> Option<PathBuf> {
+    explicit_data_dir()
+        .or_else(|| xdg_settings_path().and_then(|path| configured_data_dir(&path)))
+        .or_else(|| {
+            default_data_dir(
+                env::home_dir().as_deref(),
+                env_path("XDG_DATA_HOME").as_deref(),
+            )
+        })
+}
+
+/// 显式指定了数据目录时设置也放在其中，否则 `$XDG_CONFIG_HOME/switchhosts-rs/settings.json`
+/// 存在时使用它
+pub fn get_settings_path() -> Option<PathBuf> {
+    if explicit_data_dir().is_none() {
+        let xdg_settings = xdg_settings_path();
+        if xdg_settings.is_some() {
+            return xdg_settings;
+        }
+    }
+    resolve_data_dir().map(|dir| dir.join("settings.json"))
+}
+
+/// 依次使用命令行参数、环境变量和设置中指定的系统 hosts 路径
+pub fn resolve_hosts_file(from_settings: Option<&str>) -> Option<PathBuf> {
+    get_overrides()
+        .hosts_file
+        .or_else(|| env_path(HOSTS_FILE_ENV))
+        .or_else(|| {
+            from_settings
+                .filter(|path| !path.trim().is_empty())
+                .map(PathBuf::from)
+        })
+}
+
+/// 测试中把数据目录指向临时目录，同一时间只允许一个测试使用
+#[cfg(test)]
+pub fn with_temp_data_dir<T>(f: impl FnOnce(&Path) -> T) -> T {
+    static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = tempfile::tempdir().unwrap();
+    let previous = get_overrides();
+    set_overrides(PathOverrides {
+        data_dir: Some(dir.path().to_path_buf()),
+        ..previous.clone()
+    });
+    let res = f(dir.path());
+    set_overrides(previous);
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_data_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let home = dir.path().join("home");
+        let xdg = dir.path().join("data");
+        assert_eq!(
+            default_data_dir(Some(&home), Some(&xdg)),
+            Some(xdg.join(XDG_DIR_NAME))
+        );
+        assert_eq!(
+            default_data_dir(Some(&home), None),
+            Some(home.join(LEGACY_DIR_NAME))
+        );
+        std::fs::create_dir_all(home.join(LEGACY_DIR_NAME)).unwrap();
+        assert_eq!(
+            default_data_dir(Some(&home), Some(&xdg)),
+            Some(home.join(LEGACY_DIR_NAME))
+        );
+        assert_eq!(default_data_dir(None, None), None);
+    }
+
+    #[test]
+    fn test_configured_data_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        assert_eq!(configured_data_dir(&path), None);
+        fs::write(
+            &path,
+            r#"{ "data_dir": "/srv/switchhosts-rs", "backup_retention": 5 }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            configured_data_dir(&path),
+            Some(PathBuf::from("/srv/switchhosts-rs"))
+        );
+        fs::write(&path, r#"{ "data_dir": " " }"#).unwrap();
+        assert_eq!(configured_data_dir(&path), None);
+        fs::write(&path, "{").unwrap();
+        assert_eq!(configured_data_dir(&path), None);
+    }
+
+    #[test]
+    fn test_with_temp_data_dir() {
+        with_temp_data_dir(|dir| {
+            assert_eq!(resolve_data_dir().as_deref(), Some(dir));
+            assert_eq!(get_settings_path(), Some(dir.join("settings.json")));
+        });
+    }
+}
@@ -0,0 +1,109 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Widget},
+};
+
+/// 以对话框形式展示 unified diff
+#[derive(Debug, Default)]
+pub struct Preview {
+    title: String,
+    confirm_label: String,
+    lines: Vec<String>,
+    scroll: u16,
+    page_height: u16,
+}
+
+impl Preview {
+    pub fn new() -> Self {
+        Preview::default()
+    }
+
+    /// `confirm_label` 为按下 Enter 时执行的操作的说明
+    pub fn set_diff(
+        &mut self,
+        title: impl Into<String>,
+        diff: &str,
+        confirm_label: impl Into<String>,
+    ) {
+        self.title = title.into();
+        self.confirm_label = confirm_label.into();
+        self.lines = if diff.is_empty() {
+            vec![String::from("没有变化")]
+        } else {
+            diff.lines().map(|line| line.to_owned()).collect()
+        };
+        self.scroll = 0;
+    }
+
+    /// 返回 Some(true) 表示确认，Some(false) 表示关闭
+    pub fn handle_event(&mut self, event: KeyEvent) -> Option<bool> {
+        let max_scroll = self.lines.len().saturating_sub(1) as u16;
+        match event.code {
+            KeyCode::Enter => Some(true),
+            KeyCode::Esc | KeyCode::Char('q') => Some(false),
+            KeyCode::Up => {
+                self.scroll = self.scroll.saturating_sub(1);
+                None
+            }
+            KeyCode::Down => {
+                self.scroll = (self.scroll + 1).min(max_scroll);
+                None
+            }
+            KeyCode::PageUp => {
+                self.scroll = self.scroll.saturating_sub(self.page_height);
+                None
+            }
+            KeyCode::PageDown => {
+                self.scroll = (self.scroll + self.page_height).min(max_scroll);
+                None
+            }
+            _ => None,
+        }
+    }
+
+    pub fn line_style(line: &str) -> Style {
+        if line.starts_with("+++") || line.starts_with("---") {
+            Style::new().add_modifier(Modifier::BOLD)
+        } else if line.starts_with('+') {
+            Style::new().fg(Color::LightGreen)
+        } else if line.starts_with('-') {
+            Style::new().fg(Color::LightRed)
+        } else if line.starts_with("@@") {
+            Style::new().fg(Color::Cyan)
+        } else {
+            Style::new()
+        }
+    }
+
+    pub fn draw(&mut self, area: Rect, buf: &mut Buffer) {
+        let strong_style = Style::default()
+            .add_modifier(Modifier::BOLD)
+            .fg(Color::LightGreen);
+        let footer = Line::from(vec![
+            Span::styled(" Enter", strong_style),
+            Span::raw(format!(" {} ", self.confirm_label)),
+            Span::styled("Esc", strong_style),
+            Span::raw(" 关闭 "),
+            Span::styled("↑↓/PgUp/PgDn", strong_style),
+            Span::raw(" 滚动 "),
+        ]);
+        let block = Block::bordered()
+            .style(Style::new().white().on_black())
+            .title(self.title.clone())
+            .title_bottom(footer);
+        self.page_height = block.inner(area).height;
+        let lines = self
+            .lines
+            .iter()
+            .map(|line| Line::styled(line.as_str(), Preview::line_style(line)))
+            .collect::<Vec<_>>();
+        Paragraph::new(lines)
+            .block(block)
+            .scroll((self.scroll, 0))
+            .render(area, buf);
+    }
+}
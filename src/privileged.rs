@@ -0,0 +1,416 @@
+use std::{
+    io::{ErrorKind, Write},
+    process::{Command, Stdio},
+};
+
+use log::error;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::atomic_write::write_atomic;
+use crate::util::Result;
+
+/// 提权后执行的脚本：第一行不是标记时视为 sudo 没有读走的密码并跳过。之后的内容先写入目标所在目录的
+/// 临时文件并 fsync，再用 `mv` 替换目标，中途失败时目标文件保持不变。临时文件用 GNU cp 的 `--preserve`
+/// 继承目标的属主、权限、扩展属性和 SELinux 标签，不支持时退回到 `cp -p` 加 `chcon --reference`；
+/// 目标不能被替换（如容器中挂载的 /etc/hosts）时才退回到直接覆盖写入，与 `write_atomic` 一致。
+/// 这样密码只经过 stdin，也不需要修改目标文件的权限。脚本自身失败时以 `HELPER_FAILED` 退出，
+/// 与提权程序认证失败的退出码区分开
+const HELPER_SCRIPT: &str = r#"IFS= read -r line || exit 3
+[ "$line" = "$1" ] || { IFS= read -r line || exit 3; }
+[ "$line" = "$1" ] || exit 3
+tmp=$(mktemp "$(dirname "$2")/.switchhosts-rs.XXXXXX") || exit 3
+trap 'rm -f -- "$tmp"' EXIT
+if [ -e "$2" ]; then
+  if ! cp --preserve=mode,ownership,timestamps,xattr,context -- "$2" "$tmp" 2>/dev/null; then
+    cp --preserve=mode,ownership,timestamps,xattr -- "$2" "$tmp" 2>/dev/null || cp -p -- "$2" "$tmp" || exit 3
+    chcon --reference="$2" -- "$tmp" 2>/dev/null
+  fi
+else chmod 644 "$tmp" || exit 3; fi
+cat > "$tmp" || exit 3
+sync -- "$tmp" 2>/dev/null || sync
+err=$(LC_ALL=C mv -f -- "$tmp" "$2" 2>&1) && exit 0
+case "$err" in
+*[Bb]usy*|*[Cc]ross-device*) cat -- "$tmp" > "$2" || exit 3 ;;
+*) echo "$err" >&2; exit 3 ;;
+esac"#;
+
+/// 提权后的脚本写入失败时的退出码，sudo 和 doas 认证失败时退出码为 1
+const HELPER_FAILED: i32 = 3;
+
+/// 写入系统 hosts 时的提权方式
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Elevation {
+    /// 已经是 root 时直接写入，否则使用 sudo
+    #[default]
+    Auto,
+    Sudo,
+    Doas,
+    Pkexec,
+    Root,
+}
+
+impl Elevation {
+    pub fn resolve(self) -> Elevation {
+        match self {
+            Elevation::Auto if is_root() => Elevation::Root,
+            Elevation::Auto => Elevation::Sudo,
+            other => other,
+        }
+    }
+
+    fn default_program(self) -> &'static str {
+        match self {
+            Elevation::Doas => "doas",
+            Elevation::Pkexec => "pkexec",
+            _ => "sudo",
+        }
+    }
+}
+
+#[cfg(unix)]
+pub fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+#[cfg(not(unix))]
+pub fn is_root() -> bool {
+    false
+}
+
+/// 以提权的方式写入文件，内容通过 stdin 传给提权后的脚本
+#[derive(Clone, Debug)]
+pub struct PrivilegedWriter {
+    elevation: Elevation,
+    program: String,
+    askpass: Option<String>,
+}
+
+impl PrivilegedWriter {
+    pub fn new(elevation: Elevation) -> Self {
+        let elevation = elevation.resolve();
+        PrivilegedWriter {
+            elevation,
+            program: elevation.default_program().to_owned(),
+            askpass: None,
+        }
+    }
+
+    /// 使用 askpass 程序获取 sudo 密码（`sudo -A`），不再需要在界面中输入密码
+    pub fn with_askpass(mut self, askpass: Option<String>) -> Self {
+        self.askpass = askpass;
+        self
+    }
+
+    /// 只有 sudo 且没有配置 askpass 时需要调用方提供密码，doas 和 pkexec 自己负责认证
+    pub fn needs_password(&self) -> bool {
+        self.elevation == Elevation::Sudo && self.askpass.is_none()
+    }
+
+    /// 没有权限时给用户的提示。doas 以 `-n` 运行，会从终端而不是 stdin 读取密码，无法在界面中输入，
+    /// 需要在 doas.conf 中配置 nopass
+    pub fn permission_hint(&self) -> &'static str {
+        match self.elevation {
+            Elevation::Doas => {
+                "doas 无法在这里输入密码，请在 doas.conf 中为当前用户配置 nopass 规则"
+            }
+            _ => "请使用 sudo 运行或设置 SUDO_ASKPASS",
+        }
+    }
+
+    /// 指定提权程序的路径，测试中用来替换成假的 sudo
+    pub fn with_program(mut self, program: impl Into<String>) -> Self {
+        self.program = program.into();
+        self
+    }
+
+    fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(&self.program);
+        match self.elevation {
+            Elevation::Sudo => match &self.askpass {
+                Some(askpass) => {
+                    command.env("SUDO_ASKPASS", askpass);
+                    command.args(["-A", "-k", "--"]);
+                }
+                None => {
+                    command.args(["-S", "-k", "-p", "", "--"]);
+                }
+            },
+            Elevation::Doas => {
+                command.args(["-n", "--"]);
+            }
+            _ => {}
+        }
+        command.args(args);
+        command
+    }
+
+    pub fn write(&self, path: &str, content: &[u8], password: Option<&str>) -> Result<()> {
+        if self.elevation == Elevation::Root {
+            return write_atomic(path, content);
+        }
+        let marker = Uuid::new_v4().to_string();
+        let mut child = self
+            .command(&["sh", "-c", HELPER_SCRIPT, "switchhosts-rs", &marker, path])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let mut stdin = child.stdin.take().unwrap();
+        let res = (|| -> std::io::Result<()> {
+            if let (true, Some(password)) = (self.needs_password(), password) {
+                stdin.write_all(password.as_bytes())?;
+                stdin.write_all(b"\n")?;
+            }
+            stdin.write_all(marker.as_bytes())?;
+            stdin.write_all(b"\n")?;
+            stdin.write_all(content)
+        })();
+        drop(stdin);
+        let output = child.wait_with_output()?;
+        if output.status.code() == Some(HELPER_FAILED) {
+            return Err(color_eyre::eyre::eyre!(
+                "elevated write failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        if !output.status.success() {
+            error!(
+                "elevation failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            return Err(auth_error(&output.stderr));
+        }
+        res?;
+        Ok(())
+    }
+
+    /// 以 `sudo -S -k -v` 的方式验证密码，不执行任何命令也不修改文件。
+    /// 失败时返回 `wrong password`、`not in sudoers` 或 `sudo missing`
+    pub fn check_password(&self, password: &str) -> Result<()> {
+        if !self.needs_password() {
+            return Ok(());
+        }
+        let mut child = match Command::new(&self.program)
+            .args(["-S", "-k", "-v", "-p", ""])
+            .env("LC_ALL", "C")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(color_eyre::eyre::Error::msg("sudo missing"));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let mut stdin = child.stdin.take().unwrap();
+        let _ = stdin
+            .write_all(password.as_bytes())
+            .and_then(|_| stdin.write_all(b"\n"));
+        drop(stdin);
+        let output = child.wait_with_output()?;
+        if output.status.success() {
+            return Ok(());
+        }
+        Err(auth_error(&output.stderr))
+    }
+}
+
+/// 提权程序认证失败时的错误，`not in sudoers` 或 `wrong password`
+fn auth_error(stderr: &[u8]) -> color_eyre::eyre::Error {
+    let stderr = String::from_utf8_lossy(stderr);
+    if stderr.contains("not in the sudoers file") || stderr.contains("may not run sudo") {
+        color_eyre::eyre::Error::msg("not in sudoers")
+    } else {
+        color_eyre::eyre::Error::msg("wrong password")
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::{
+        fs,
+        os::unix::fs::{MetadataExt, PermissionsExt},
+        path::Path,
+    };
+
+    /// 假的 sudo：先执行 `check` 验证密码，带 `-v` 时验证后直接退出，否则执行 `--` 之后的命令
+    fn fake_sudo(dir: &Path, name: &str, check: &str) -> String {
+        let path = dir.join(name);
+        let script = format!(
+            "#!/bin/sh\n{}\nfor arg; do [ \"$arg\" = -v ] && exit 0; done\n\
+             while [ \"$1\" != -- ]; do shift; done\nshift\nexec \"$@\"\n",
+            check
+        );
+        fs::write(&path, script).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    const CHECK_SECRET: &str =
+        "IFS= read -r password; [ \"$password\" = secret ] || { echo 'Sorry, try again.' >&2; exit 1; }";
+
+    /// 带 `-A` 时从 SUDO_ASKPASS 获取密码
+    const CHECK_ASKPASS: &str =
+        "[ \"$1\" = -A ] && [ \"$(\"$SUDO_ASKPASS\")\" = secret ] || exit 1";
+
+    #[test]
+    fn test_privileged_write() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let target = dir.path().join("hosts");
+        fs::write(&target, "old\n")?;
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o644))?;
+        let target_path = target.to_string_lossy().into_owned();
+
+        let writer = PrivilegedWriter::new(Elevation::Sudo).with_program(fake_sudo(
+            dir.path(),
+            "sudo",
+            CHECK_SECRET,
+        ));
+        let e = writer
+            .write(&target_path, b"bad\n", Some("wrong"))
+            .unwrap_err();
+        assert_eq!(e.to_string(), "wrong password");
+        assert_eq!(fs::read_to_string(&target)?, "old\n");
+        let inode = fs::metadata(&target)?.ino();
+        writer.write(&target_path, b"127.0.0.1 a.test\n", Some("secret"))?;
+        assert_eq!(fs::read_to_string(&target)?, "127.0.0.1 a.test\n");
+        assert_eq!(fs::metadata(&target)?.permissions().mode() & 0o777, 0o644);
+        // 通过临时文件替换目标，不会留下临时文件
+        assert_ne!(fs::metadata(&target)?.ino(), inode);
+        assert!(fs::read_dir(dir.path())?.all(|entry| !entry
+            .unwrap()
+            .file_name()
+            .to_string_lossy()
+            .starts_with(".switchhosts-rs")));
+
+        // 脚本自身写入失败（如目录不存在）时不是认证失败
+        let missing = dir
+            .path()
+            .join("missing/hosts")
+            .to_string_lossy()
+            .into_owned();
+        let e = writer
+            .write(&missing, b"bad\n", Some("secret"))
+            .unwrap_err();
+        assert!(e.to_string().starts_with("elevated write failed"));
+
+        // sudo 没有读走密码时，密码不能写进文件
+        let writer = PrivilegedWriter::new(Elevation::Sudo).with_program(fake_sudo(
+            dir.path(),
+            "sudo-nopasswd",
+            "",
+        ));
+        writer.write(&target_path, b"10.0.0.1 b.test\n", Some("secret"))?;
+        assert_eq!(fs::read_to_string(&target)?, "10.0.0.1 b.test\n");
+
+        // 配置了 askpass 时通过 sudo -A 获取密码，不需要调用方提供
+        let askpass = fake_sudo(dir.path(), "askpass", "echo secret; exit 0");
+        let writer = PrivilegedWriter::new(Elevation::Sudo)
+            .with_program(fake_sudo(dir.path(), "sudo-askpass", CHECK_ASKPASS))
+            .with_askpass(Some(askpass));
+        assert!(!writer.needs_password());
+        writer.write(&target_path, b"10.0.0.2 c.test\n", None)?;
+        assert_eq!(fs::read_to_string(&target)?, "10.0.0.2 c.test\n");
+        Ok(())
+    }
+
+    /// 假的 cp：记录参数，带 `context` 时失败（如没有启用 SELinux），否则交给真正的 cp
+    fn fake_cp(dir: &Path) -> String {
+        let real = Command::new("sh")
+            .args(["-c", "command -v cp"])
+            .output()
+            .unwrap();
+        let real = String::from_utf8_lossy(&real.stdout).trim().to_owned();
+        let bin = dir.join("bin");
+        fs::create_dir(&bin).unwrap();
+        let path = bin.join("cp");
+        let script = format!(
+            "#!/bin/sh
+echo \"$*\" >> \"{}\"
+case \"$*\" in *context*) exit 1 ;; esac
+exec \"{}\" \"$@\"\n",
+            dir.join("cp.log").display(),
+            real
+        );
+        fs::write(&path, script).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        bin.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_privileged_write_keeps_xattrs() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let target = dir.path().join("hosts");
+        fs::write(&target, "old\n")?;
+        let has_xattr = xattr::set(&target, "user.switchhosts", b"1").is_ok();
+        let target_path = target.to_string_lossy().into_owned();
+
+        // 真正的 cp 一次就能保留扩展属性
+        let writer = PrivilegedWriter::new(Elevation::Sudo).with_program(fake_sudo(
+            dir.path(),
+            "sudo",
+            CHECK_SECRET,
+        ));
+        writer.write(&target_path, b"10.0.0.1 a.test\n", Some("secret"))?;
+        assert_eq!(fs::read_to_string(&target)?, "10.0.0.1 a.test\n");
+        if has_xattr {
+            assert_eq!(
+                xattr::get(&target, "user.switchhosts")?,
+                Some(b"1".to_vec())
+            );
+        }
+
+        // 不支持保留 SELinux 标签时退回到只保留扩展属性
+        let bin = fake_cp(dir.path());
+        let writer = PrivilegedWriter::new(Elevation::Sudo).with_program(fake_sudo(
+            dir.path(),
+            "sudo-fake-cp",
+            &format!("PATH=\"{bin}:$PATH\"; export PATH; {CHECK_SECRET}"),
+        ));
+        writer.write(&target_path, b"10.0.0.2 b.test\n", Some("secret"))?;
+        assert_eq!(fs::read_to_string(&target)?, "10.0.0.2 b.test\n");
+        if has_xattr {
+            assert_eq!(
+                xattr::get(&target, "user.switchhosts")?,
+                Some(b"1".to_vec())
+            );
+        }
+        let log = fs::read_to_string(dir.path().join("cp.log"))?;
+        let calls = log.lines().collect::<Vec<_>>();
+        assert_eq!(calls.len(), 2);
+        assert!(calls[0].starts_with("--preserve=mode,ownership,timestamps,xattr,context "));
+        assert!(calls[1].starts_with("--preserve=mode,ownership,timestamps,xattr "));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_password() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let check = |program: String, password: &str| {
+            PrivilegedWriter::new(Elevation::Sudo)
+                .with_program(program)
+                .check_password(password)
+                .map_err(|e| e.to_string())
+        };
+        let sudo = fake_sudo(dir.path(), "sudo", CHECK_SECRET);
+        assert_eq!(check(sudo.clone(), "secret"), Ok(()));
+        assert_eq!(check(sudo, "wrong"), Err("wrong password".to_owned()));
+        let not_in_sudoers = fake_sudo(
+            dir.path(),
+            "sudo-denied",
+            "echo 'alice is not in the sudoers file.' >&2; exit 1",
+        );
+        assert_eq!(
+            check(not_in_sudoers, "secret"),
+            Err("not in sudoers".to_owned())
+        );
+        let missing = dir.path().join("missing").to_string_lossy().into_owned();
+        assert_eq!(check(missing, "secret"), Err("sudo missing".to_owned()));
+        Ok(())
+    }
+}
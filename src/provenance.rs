@@ -0,0 +1,215 @@
+use chrono::{DateTime, Local};
+
+use crate::data::ConfigItem;
+
+const PROFILE_START: &str = "# --- SWITCHHOSTS_RS_PROFILE_START";
+
+const PROFILE_END: &str = "# --- SWITCHHOSTS_RS_PROFILE_END";
+
+const MARKER_SUFFIX: &str = " ---";
+
+/// 受管理区块中的一段内容，来自某个 hosts 时带有它的 id、标题和修改时间
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProfileSection {
+    /// 不属于任何 hosts 的内容（如手动添加的行）为 None
+    pub id: Option<String>,
+    pub title: String,
+    pub modified: Option<String>,
+    pub content: String,
+}
+
+impl ProfileSection {
+    fn label(&self) -> String {
+        format!(
+            "{}（{}）",
+            self.title,
+            self.id.as_deref().unwrap_or_default()
+        )
+    }
+}
+
+/// 在 hosts 内容前后加上来源注释，记录标题、id 和最后修改时间
+pub fn wrap_profile(item: &ConfigItem, modified: Option<DateTime<Local>>, content: &str) -> String {
+    let modified = modified
+        .map(|modified| modified.format("%Y-%m-%dT%H:%M:%S%:z").to_string())
+        .unwrap_or("-".to_owned());
+    let mut wrapped = format!(
+        "{} id={} modified={} title={}{}\n",
+        PROFILE_START,
+        item.id(),
+        modified,
+        item.title(),
+        MARKER_SUFFIX
+    );
+    let content = content.trim_end_matches(['\r', '\n']);
+    if !content.is_empty() {
+        wrapped.push_str(content);
+        wrapped.push('\n');
+    }
+    wrapped.push_str(&format!(
+        "{} id={}{}",
+        PROFILE_END,
+        item.id(),
+        MARKER_SUFFIX
+    ));
+    wrapped
+}
+
+fn parse_header(line: &str) -> Option<ProfileSection> {
+    let fields = line
+        .trim_end()
+        .strip_prefix(PROFILE_START)?
+        .strip_suffix(MARKER_SUFFIX)?
+        .trim_start();
+    let (id, fields) = fields.strip_prefix("id=")?.split_once(' ')?;
+    let (modified, title) = fields.strip_prefix("modified=")?.split_once(' ')?;
+    Some(ProfileSection {
+        id: Some(id.to_owned()),
+        title: title.strip_prefix("title=")?.to_owned(),
+        modified: Some(modified).filter(|m| *m != "-").map(str::to_owned),
+        content: String::new(),
+    })
+}
+
+fn is_footer(line: &str) -> bool {
+    line.trim_end().starts_with(PROFILE_END)
+}
+
+/// 按来源注释把受管理区块拆分为各个 hosts 的内容，注释以外的非空内容作为不属于任何 hosts 的一段。
+/// 旧版本写入的区块没有来源注释，整个区块都不属于任何 hosts
+pub fn parse_block(block: &str) -> Vec<ProfileSection> {
+    let mut sections = vec![];
+    let mut current: Option<(ProfileSection, Vec<&str>)> = None;
+    let mut untracked: Vec<&str> = vec![];
+    let flush_untracked = |sections: &mut Vec<ProfileSection>, untracked: &mut Vec<&str>| {
+        if untracked.iter().any(|line| !line.trim().is_empty()) {
+            sections.push(ProfileSection {
+                content: untracked.join("\n"),
+                ..ProfileSection::default()
+            });
+        }
+        untracked.clear();
+    };
+    for line in block.lines() {
+        if let Some(header) = parse_header(line) {
+            if let Some((mut section, lines)) = current.take() {
+                section.content = lines.join("\n");
+                sections.push(section);
+            }
+            flush_untracked(&mut sections, &mut untracked);
+            current = Some((header, vec![]));
+        } else if is_footer(line) {
+            if let Some((mut section, lines)) = current.take() {
+                section.content = lines.join("\n");
+                sections.push(section);
+            }
+        } else if let Some((_, lines)) = current.as_mut() {
+            lines.push(line);
+        } else {
+            untracked.push(line);
+        }
+    }
+    if let Some((mut section, lines)) = current.take() {
+        section.content = lines.join("\n");
+        sections.push(section);
+    }
+    flush_untracked(&mut sections, &mut untracked);
+    sections
+}
+
+/// 去掉来源注释后的内容
+pub fn strip_provenance(block: &str) -> String {
+    parse_block(block)
+        .into_iter()
+        .map(|section| section.content)
+        .filter(|content| !content.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 去掉空行和行尾空白后的各行，用来比较受管理区块的内容
+pub fn normalize_block(content: &str) -> Vec<&str> {
+    content
+        .lines()
+        .map(|line| line.trim_end())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// 按来源比较按配置生成的区块和系统 hosts 中的实际区块，描述每个 hosts 的差异
+pub fn describe_changes(expected: &str, live: &str) -> Vec<String> {
+    let expected = parse_block(expected);
+    let live = parse_block(live);
+    let mut changes = vec![];
+    for section in expected.iter().filter(|section| section.id.is_some()) {
+        match live.iter().find(|other| other.id == section.id) {
+            None => changes.push(format!("{}：系统 hosts 中缺少", section.label())),
+            Some(other) if normalize_block(&other.content) != normalize_block(&section.content) => {
+                changes.push(format!("{}：内容被修改", section.label()))
+            }
+            _ => {}
+        }
+    }
+    for section in live.iter() {
+        match &section.id {
+            Some(_) if !expected.iter().any(|other| other.id == section.id) => changes.push(
+                format!("{}：已停用或已删除，但仍在系统 hosts 中", section.label()),
+            ),
+            None => changes.push(format!(
+                "{} 行不属于任何 hosts",
+                normalize_block(&section.content).len()
+            )),
+            _ => {}
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::ConfigItemType;
+
+    #[test]
+    fn test_parse_block() {
+        let a = ConfigItem::new(
+            "a1".to_owned(),
+            true,
+            "dev (a)".to_owned(),
+            ConfigItemType::User,
+        );
+        let b = ConfigItem::new("b1".to_owned(), true, "b".to_owned(), ConfigItemType::User);
+        let block = format!(
+            "\n{}\n{}\n# manual\n10.0.0.9 x.test",
+            wrap_profile(&a, None, "10.0.0.1 a.test\n10.0.0.2 b.test\n"),
+            wrap_profile(&b, None, "")
+        );
+        let sections = parse_block(&block);
+        assert_eq!(sections.len(), 3);
+        assert_eq!(sections[0].id.as_deref(), Some("a1"));
+        assert_eq!(sections[0].title, "dev (a)");
+        assert_eq!(sections[0].modified, None);
+        assert_eq!(sections[0].content, "10.0.0.1 a.test\n10.0.0.2 b.test");
+        assert_eq!(sections[1].content, "");
+        assert_eq!(sections[2].id, None);
+        assert_eq!(
+            strip_provenance(&block),
+            "10.0.0.1 a.test\n10.0.0.2 b.test\n# manual\n10.0.0.9 x.test"
+        );
+
+        let edited = block.replace("10.0.0.2", "10.0.0.3");
+        let expected = format!(
+            "\n{}",
+            wrap_profile(&a, None, "10.0.0.1 a.test\n10.0.0.2 b.test")
+        );
+        assert_eq!(
+            describe_changes(&expected, &edited),
+            vec![
+                "dev (a)（a1）：内容被修改",
+                "b（b1）：已停用或已删除，但仍在系统 hosts 中",
+                "2 行不属于任何 hosts",
+            ]
+        );
+        assert!(describe_changes(&expected, &expected).is_empty());
+    }
+}
@@ -0,0 +1,95 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Widget},
+};
+
+use crate::preview::Preview;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReconcileAction {
+    /// 按配置重新写入系统 hosts
+    Reapply,
+    /// 把系统 hosts 中的实际内容导入为新的 hosts
+    Adopt,
+    /// 停用所有 hosts
+    DisableAll,
+    /// 暂不处理
+    Ignore,
+}
+
+/// 启动时系统 hosts 的受管理区块与配置不一致，展示差异并选择处理方式
+#[derive(Debug, Default)]
+pub struct ReconcileDialog {
+    lines: Vec<String>,
+    scroll: u16,
+}
+
+impl ReconcileDialog {
+    pub fn new() -> Self {
+        ReconcileDialog::default()
+    }
+
+    /// 先列出每个 hosts 的差异，再展示完整的 diff
+    pub fn set_diff(&mut self, changes: &[String], diff: &str) {
+        self.lines = changes.to_vec();
+        if !changes.is_empty() {
+            self.lines.push(String::new());
+        }
+        self.lines.extend(diff.lines().map(|line| line.to_owned()));
+        self.scroll = 0;
+    }
+
+    pub fn handle_event(&mut self, event: KeyEvent) -> Option<ReconcileAction> {
+        match event.code {
+            KeyCode::Char('r') | KeyCode::Char('R') => Some(ReconcileAction::Reapply),
+            KeyCode::Char('a') | KeyCode::Char('A') => Some(ReconcileAction::Adopt),
+            KeyCode::Char('o') | KeyCode::Char('O') => Some(ReconcileAction::DisableAll),
+            KeyCode::Esc => Some(ReconcileAction::Ignore),
+            KeyCode::Up => {
+                self.scroll = self.scroll.saturating_sub(1);
+                None
+            }
+            KeyCode::Down => {
+                if (self.scroll as usize) + 1 < self.lines.len() {
+                    self.scroll += 1;
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    pub fn draw(&mut self, area: Rect, buf: &mut Buffer) {
+        let strong_style = Style::default()
+            .add_modifier(Modifier::BOLD)
+            .fg(Color::LightGreen);
+        let footer = Line::from(vec![
+            Span::styled(" R", strong_style),
+            Span::raw(" 按配置重新写入 "),
+            Span::styled("A", strong_style),
+            Span::raw(" 导入为新的 hosts "),
+            Span::styled("O", strong_style),
+            Span::raw(" 全部停用 "),
+            Span::styled("Esc", strong_style),
+            Span::raw(" 暂不处理 "),
+        ]);
+        let block = Block::bordered()
+            .style(Style::new().white().on_black())
+            .border_style(Style::new().yellow())
+            .title("系统 hosts 与配置不一致")
+            .title_bottom(footer);
+        let lines = self
+            .lines
+            .iter()
+            .map(|line| Line::styled(line.as_str(), Preview::line_style(line)))
+            .collect::<Vec<_>>();
+        Paragraph::new(lines)
+            .block(block)
+            .scroll((self.scroll, 0))
+            .render(area, buf);
+    }
+}
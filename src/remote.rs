@@ -0,0 +1,185 @@
+use std::{sync::mpsc::Sender, thread, time::Duration};
+
+use chrono::Utc;
+
+use crate::data::RemoteSource;
+use crate::util::Result;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FetchResult {
+    /// 服务端返回 304，本地缓存仍然有效
+    NotModified,
+    Updated {
+        content: String,
+        etag: Option<String>,
+        last_modified: Option<String>,
+    },
+}
+
+/// 拉取远程 hosts，带上 ETag / Last-Modified 做条件请求
+pub fn fetch_remote(
+    url: &str,
+    etag: Option<&str>,
+    last_modified: Option<&str>,
+) -> Result<FetchResult> {
+    let mut request = ureq::get(url).timeout(FETCH_TIMEOUT);
+    if let Some(etag) = etag {
+        request = request.set("If-None-Match", etag);
+    }
+    if let Some(last_modified) = last_modified {
+        request = request.set("If-Modified-Since", last_modified);
+    }
+    let response = request.call().map_err(|e| match e {
+        ureq::Error::Status(code, _) => color_eyre::eyre::eyre!("HTTP {}", code),
+        other => color_eyre::eyre::Error::msg(other.to_string()),
+    })?;
+    if response.status() == 304 {
+        return Ok(FetchResult::NotModified);
+    }
+    let etag = response.header("ETag").map(|s| s.to_owned());
+    let last_modified = response.header("Last-Modified").map(|s| s.to_owned());
+    let content = response.into_string()?;
+    Ok(FetchResult::Updated {
+        content,
+        etag,
+        last_modified,
+    })
+}
+
+/// 在后台线程拉取，结果连同 hosts id 一起发送给 `sender`
+pub fn spawn_fetch(
+    id: String,
+    remote: &RemoteSource,
+    sender: Sender<(String, Result<FetchResult>)>,
+) {
+    let RemoteSource {
+        url,
+        etag,
+        last_modified,
+        ..
+    } = remote.clone();
+    thread::spawn(move || {
+        let res = fetch_remote(&url, etag.as_deref(), last_modified.as_deref());
+        let _ = sender.send((id, res));
+    });
+}
+
+/// 根据拉取结果更新远程 hosts 的状态，返回新的内容（内容未变化时为 None）
+pub fn apply_fetch_result(remote: &mut RemoteSource, res: Result<FetchResult>) -> Option<String> {
+    match res {
+        Ok(FetchResult::NotModified) => {
+            remote.last_updated = Some(Utc::now().timestamp());
+            remote.last_error = None;
+            None
+        }
+        Ok(FetchResult::Updated {
+            content,
+            etag,
+            last_modified,
+        }) => {
+            remote.etag = etag;
+            remote.last_modified = last_modified;
+            remote.last_updated = Some(Utc::now().timestamp());
+            remote.last_error = None;
+            Some(content)
+        }
+        Err(e) => {
+            remote.last_error = Some(e.to_string());
+            None
+        }
+    }
+}
+
+/// 是否到了自动刷新的时间，拉取失败的也按间隔重试
+pub fn is_refresh_due(remote: &RemoteSource, now: i64, last_attempt: Option<i64>) -> bool {
+    if remote.refresh_interval == 0 {
+        return false;
+    }
+    match last_attempt.or(remote.last_updated) {
+        Some(time) => now - time >= remote.refresh_interval as i64,
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+    };
+
+    /// 本地的 HTTP 服务，带 If-None-Match: "v1" 的请求返回 304
+    fn serve(requests: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut not_modified = false;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.to_lowercase().starts_with("if-none-match: \"v1\"") {
+                        not_modified = true;
+                    }
+                    if line == "\r\n" || line.is_empty() {
+                        break;
+                    }
+                }
+                let response = if not_modified {
+                    String::from("HTTP/1.1 304 Not Modified\r\nContent-Length: 0\r\n\r\n")
+                } else {
+                    let body = "10.0.0.1 api.dev\n";
+                    format!(
+                        "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: {}\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                };
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        format!("http://{}/hosts", addr)
+    }
+
+    #[test]
+    fn test_fetch_remote() {
+        let url = serve(2);
+        let mut remote = RemoteSource {
+            url: url.clone(),
+            ..Default::default()
+        };
+        let res = fetch_remote(&url, None, None);
+        assert_eq!(
+            apply_fetch_result(&mut remote, res),
+            Some("10.0.0.1 api.dev\n".to_owned())
+        );
+        assert_eq!(remote.etag, Some("\"v1\"".to_owned()));
+        let res = fetch_remote(&url, remote.etag.as_deref(), None);
+        assert_eq!(res.unwrap(), FetchResult::NotModified);
+    }
+
+    #[test]
+    fn test_fetch_remote_failed() {
+        let mut remote = RemoteSource::default();
+        let res = fetch_remote("http://127.0.0.1:1/hosts", None, None);
+        assert_eq!(apply_fetch_result(&mut remote, res), None);
+        assert!(remote.last_error.is_some());
+    }
+
+    #[test]
+    fn test_is_refresh_due() {
+        let remote = RemoteSource {
+            refresh_interval: 60,
+            last_updated: Some(1000),
+            ..Default::default()
+        };
+        assert!(!is_refresh_due(&remote, 1030, None));
+        assert!(is_refresh_due(&remote, 1060, None));
+        assert!(!is_refresh_due(&remote, 1060, Some(1050)));
+    }
+}
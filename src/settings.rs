@@ -0,0 +1,101 @@
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::{env, fs, path::PathBuf};
+
+use crate::atomic_write::write_atomic;
+use crate::data::check_switch_host_rs_dir_exist;
+use crate::paths;
+use crate::privileged::{Elevation, PrivilegedWriter};
+use crate::target::Target;
+use crate::util::Result;
+
+const DEFAULT_BACKUP_RETENTION: usize = 20;
+
+const DEFAULT_REMOTE_REFRESH_INTERVAL: u64 = 3600;
+
+const DEFAULT_PASSWORD_CACHE_TIMEOUT: u64 = 300;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    /// 内容校验不通过的 hosts 不允许启用
+    pub block_invalid_profiles: bool,
+    /// 系统 hosts 备份保留的份数
+    pub backup_retention: usize,
+    /// 新建远程 hosts 时默认的自动刷新间隔（秒）
+    pub remote_refresh_interval: u64,
+    /// 写入系统 hosts 时的提权方式
+    pub elevation: Elevation,
+    /// 缓存的 sudo 密码在多久没有使用后清除（秒），为 0 时不缓存
+    pub password_cache_timeout: u64,
+    /// 获取 sudo 密码的 askpass 程序，为空时使用环境变量 SUDO_ASKPASS
+    pub askpass: Option<String>,
+    /// 数据目录，只在 `$XDG_CONFIG_HOME/switchhosts-rs/settings.json` 中有效
+    pub data_dir: Option<String>,
+    /// 系统 hosts 的路径，为空时使用系统默认路径
+    pub hosts_file: Option<String>,
+    /// 系统 hosts 中受管理区块的标记名称，为空时使用 SWITCHHOSTS_RS_CONTENT
+    pub marker: Option<String>,
+    /// 系统 hosts 中需要迁移的旧标记名称，写入时改为当前的标记
+    pub migrate_markers: Vec<String>,
+    /// 除系统 hosts 外可以写入的其他 hosts 文件
+    pub targets: Vec<Target>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            block_invalid_profiles: false,
+            backup_retention: DEFAULT_BACKUP_RETENTION,
+            remote_refresh_interval: DEFAULT_REMOTE_REFRESH_INTERVAL,
+            elevation: Elevation::default(),
+            password_cache_timeout: DEFAULT_PASSWORD_CACHE_TIMEOUT,
+            askpass: None,
+            data_dir: None,
+            hosts_file: None,
+            marker: None,
+            migrate_markers: vec![],
+            targets: vec![],
+        }
+    }
+}
+
+impl Settings {
+    /// 设置中的 askpass 优先，其次是环境变量 SUDO_ASKPASS
+    pub fn askpass(&self) -> Option<String> {
+        self.askpass
+            .clone()
+            .or_else(|| env::var("SUDO_ASKPASS").ok())
+            .filter(|askpass| !askpass.trim().is_empty())
+    }
+
+    pub fn privileged_writer(&self) -> PrivilegedWriter {
+        PrivilegedWriter::new(self.elevation).with_askpass(self.askpass())
+    }
+}
+
+pub fn get_settings_path() -> Option<PathBuf> {
+    paths::get_settings_path()
+}
+
+/// 读取设置，文件不存在或格式错误时使用默认设置
+pub fn read_settings() -> Settings {
+    let path = match get_settings_path() {
+        Some(path) => path,
+        None => return Settings::default(),
+    };
+    match fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            error!("{e}");
+            Settings::default()
+        }),
+        Err(_) => Settings::default(),
+    }
+}
+
+pub fn write_settings(settings: &Settings) -> Result<()> {
+    check_switch_host_rs_dir_exist()?;
+    let path = get_settings_path().unwrap();
+    write_atomic(&path, serde_json::to_string_pretty(settings)?)?;
+    Ok(())
+}
@@ -7,6 +7,7 @@ use ratatui::{
     widgets::{Block, Borders, Widget},
 };
 use tui_textarea::{CursorMove, TextArea};
+use zeroize::Zeroizing;
 
 #[derive(Debug, Default, PartialEq)]
 pub enum SinglelineTextareaType {
@@ -25,10 +26,7 @@ pub struct SingleLineTextarea<'a> {
 
 impl<'a> SingleLineTextarea<'a> {
     pub fn new(place_holder: String, title: String, r#type: SinglelineTextareaType) -> Self {
-        let mut textarea = create_new_textarea(place_holder);
-        if r#type == SinglelineTextareaType::Password {
-            textarea.set_mask_char('*');
-        }
+        let textarea = create_typed_textarea(place_holder, &r#type);
         let t = SingleLineTextarea {
             textarea,
             title,
@@ -49,6 +47,13 @@ impl<'a> SingleLineTextarea<'a> {
         self.textarea = textarea;
     }
 
+    /// 取出输入的内容并清空输入框，输入框中原来的内容在释放时清零，用于密码
+    pub fn take_secret(&mut self) -> Zeroizing<String> {
+        let textarea = create_typed_textarea(self.textarea.placeholder_text(), &self.r#type);
+        let lines = Zeroizing::new(std::mem::replace(&mut self.textarea, textarea).into_lines());
+        Zeroizing::new(lines.concat())
+    }
+
     pub fn input(&mut self, event: KeyEvent) {
         self.textarea.input(event);
     }
@@ -57,6 +62,10 @@ impl<'a> SingleLineTextarea<'a> {
         self.textarea.move_cursor(CursorMove::End);
     }
 
+    pub fn set_title(&mut self, title: impl Into<String>) {
+        self.title = title.into();
+    }
+
     pub fn set_error(&mut self, error_title: impl Into<String>) {
         self.error_title = error_title.into();
     }
@@ -81,6 +90,19 @@ impl<'a> SingleLineTextarea<'a> {
     }
 }
 
+/// 密码输入框显示为 `*`，并且不保留撤销历史，避免在历史中留下密码的副本
+fn create_typed_textarea<'a>(
+    place_holder: impl Into<String>,
+    r#type: &SinglelineTextareaType,
+) -> TextArea<'a> {
+    let mut textarea = create_new_textarea(place_holder);
+    if *r#type == SinglelineTextareaType::Password {
+        textarea.set_mask_char('*');
+        textarea.set_max_histories(0);
+    }
+    textarea
+}
+
 pub fn create_new_single_line_textarea<'a>(
     place_holder: impl Into<String>,
     title: impl Into<String>,
@@ -98,4 +120,23 @@ pub fn create_new_single_line_textarea2 (
 ) {
     let b = place_holder.into();
    
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    #[test]
+    fn test_take_secret() {
+        let mut textarea =
+            create_new_single_line_textarea("", "密码", SinglelineTextareaType::Password);
+        for c in "secret".chars() {
+            textarea.input(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+        assert_eq!(textarea.take_secret().as_str(), "secret");
+        assert_eq!(textarea.get_text(), "");
+        // 清空后仍然是密码输入框
+        assert_eq!(textarea.textarea.mask_char(), Some('*'));
+    }
+}
@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+
+use crate::hosts::{get_sys_hosts_path, is_valid_marker_name, BlockMarker};
+use crate::settings::read_settings;
+use crate::util::Result;
+
+/// 系统 hosts 对应的目标名称，没有指定目标的 hosts 都写入这里
+pub const SYSTEM_TARGET: &str = "system";
+
+/// 写入 hosts 的目标文件，如宿主机的系统 hosts 和容器中挂载的 hosts，每个文件有各自的受管理区块
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Target {
+    pub name: String,
+    pub path: String,
+    /// 受管理区块的标记名称，为空时使用 SWITCHHOSTS_RS_CONTENT
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub marker: Option<String>,
+    /// 需要迁移的旧标记名称，写入时改为当前的标记
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub migrate_markers: Vec<String>,
+}
+
+impl Target {
+    pub fn system() -> Self {
+        let settings = read_settings();
+        Target {
+            name: SYSTEM_TARGET.to_owned(),
+            path: get_sys_hosts_path(),
+            marker: settings.marker,
+            migrate_markers: settings.migrate_markers,
+        }
+    }
+
+    pub fn is_system(&self) -> bool {
+        self.name == SYSTEM_TARGET
+    }
+
+    /// 当前的标记排在第一个，之后是需要迁移的旧标记，有无效的名称时返回 `invalid marker`
+    pub fn block_markers(&self) -> Result<Vec<BlockMarker>> {
+        let mut markers = vec![match self.marker.as_deref() {
+            Some(name) => BlockMarker::new(name)?,
+            None => BlockMarker::default(),
+        }];
+        for name in &self.migrate_markers {
+            let marker = BlockMarker::new(name)?;
+            if !markers.contains(&marker) {
+                markers.push(marker);
+            }
+        }
+        Ok(markers)
+    }
+
+    /// 设置中无效的标记名称
+    pub fn invalid_markers(&self) -> Vec<String> {
+        self.marker
+            .iter()
+            .chain(self.migrate_markers.iter())
+            .filter(|name| !is_valid_marker_name(name))
+            .cloned()
+            .collect()
+    }
+}
+
+/// 系统 hosts 排在第一个，之后是设置中的目标，名称或路径为空以及重名的目标会被忽略
+pub fn get_targets() -> Vec<Target> {
+    let mut targets = vec![Target::system()];
+    for target in read_settings().targets {
+        if target.name.trim().is_empty()
+            || target.path.trim().is_empty()
+            || targets.iter().any(|other| other.name == target.name)
+        {
+            continue;
+        }
+        targets.push(target);
+    }
+    targets
+}
+
+/// 所有目标（包括系统 hosts）中无效的标记名称，启动时检查并提示
+pub fn invalid_markers() -> Vec<String> {
+    get_targets()
+        .iter()
+        .flat_map(|target| target.invalid_markers())
+        .collect()
+}
+
+/// 标记名称无效时给用户的提示
+pub fn invalid_marker_message() -> String {
+    format!(
+        "设置中的标记名称无效：{}，只能包含字母、数字、_ 和 -",
+        invalid_markers().join(", ")
+    )
+}
+
+pub fn find_target(name: &str) -> Option<Target> {
+    get_targets().into_iter().find(|target| target.name == name)
+}
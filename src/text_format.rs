@@ -0,0 +1,194 @@
+/// 文本文件的编码，UTF-16 只识别带 BOM 的文件
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+
+    /// 把所有换行符统一为这种换行符
+    pub fn apply(self, text: &str) -> String {
+        normalize_line_endings(text).replace('\n', self.as_str())
+    }
+}
+
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+const UTF16LE_BOM: &[u8] = b"\xff\xfe";
+
+const UTF16BE_BOM: &[u8] = b"\xfe\xff";
+
+/// hosts 文件和 hosts 内容文件的换行符、BOM 与编码，写回时保持与原文件一致
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TextFormat {
+    pub encoding: Encoding,
+    pub bom: bool,
+    pub line_ending: LineEnding,
+}
+
+impl TextFormat {
+    /// 根据 BOM 判断编码，换行符取文件中出现较多的一种，相同时使用 `\n`
+    pub fn detect(content: &[u8]) -> Self {
+        let (encoding, bom) = if content.starts_with(UTF8_BOM) {
+            (Encoding::Utf8, true)
+        } else if content.starts_with(UTF16LE_BOM) {
+            (Encoding::Utf16Le, true)
+        } else if content.starts_with(UTF16BE_BOM) {
+            (Encoding::Utf16Be, true)
+        } else {
+            (Encoding::Utf8, false)
+        };
+        let mut format = TextFormat {
+            encoding,
+            bom,
+            line_ending: LineEnding::Lf,
+        };
+        let text = format.decode(content);
+        let crlf = text.matches("\r\n").count();
+        if crlf > text.matches('\n').count() - crlf {
+            format.line_ending = LineEnding::CrLf;
+        }
+        format
+    }
+
+    fn bom_bytes(&self) -> &'static [u8] {
+        match (self.bom, self.encoding) {
+            (false, _) => b"",
+            (true, Encoding::Utf8) => UTF8_BOM,
+            (true, Encoding::Utf16Le) => UTF16LE_BOM,
+            (true, Encoding::Utf16Be) => UTF16BE_BOM,
+        }
+    }
+
+    /// 去掉 BOM 后解码，换行符保持不变，无法解码的字节显示为替换字符
+    pub fn decode(&self, content: &[u8]) -> String {
+        let content = content.strip_prefix(self.bom_bytes()).unwrap_or(content);
+        let units = content.chunks_exact(2);
+        match self.encoding {
+            Encoding::Utf8 => String::from_utf8_lossy(content).into_owned(),
+            Encoding::Utf16Le => String::from_utf16_lossy(
+                &units
+                    .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+                    .collect::<Vec<_>>(),
+            ),
+            Encoding::Utf16Be => String::from_utf16_lossy(
+                &units
+                    .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+                    .collect::<Vec<_>>(),
+            ),
+        }
+    }
+
+    /// 与 `decode` 相同，但有无法解码的字节时返回 None，用于需要原样写回的场景
+    pub fn decode_strict(&self, content: &[u8]) -> Option<String> {
+        let content = content.strip_prefix(self.bom_bytes()).unwrap_or(content);
+        if content.len() % 2 == 1 && self.encoding != Encoding::Utf8 {
+            return None;
+        }
+        let units = content.chunks_exact(2);
+        match self.encoding {
+            Encoding::Utf8 => String::from_utf8(content.to_vec()).ok(),
+            Encoding::Utf16Le => String::from_utf16(
+                &units
+                    .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+                    .collect::<Vec<_>>(),
+            )
+            .ok(),
+            Encoding::Utf16Be => String::from_utf16(
+                &units
+                    .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+                    .collect::<Vec<_>>(),
+            )
+            .ok(),
+        }
+    }
+
+    /// 按原文件的编码编码并加上 BOM，换行符保持不变
+    pub fn encode(&self, text: &str) -> Vec<u8> {
+        let mut content = self.bom_bytes().to_vec();
+        match self.encoding {
+            Encoding::Utf8 => content.extend_from_slice(text.as_bytes()),
+            Encoding::Utf16Le => text
+                .encode_utf16()
+                .for_each(|unit| content.extend_from_slice(&unit.to_le_bytes())),
+            Encoding::Utf16Be => text
+                .encode_utf16()
+                .for_each(|unit| content.extend_from_slice(&unit.to_be_bytes())),
+        }
+        content
+    }
+}
+
+/// 把 `\r\n` 统一为 `\n`
+pub fn normalize_line_endings(text: &str) -> String {
+    text.replace("\r\n", "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(TextFormat::detect(b""), TextFormat::default());
+        let format = TextFormat::detect(b"127.0.0.1 localhost\r\n::1 localhost\r\n");
+        assert_eq!(format.line_ending, LineEnding::CrLf);
+        assert!(!format.bom);
+
+        // 换行符混用时取较多的一种
+        let mixed = b"a\r\nb\r\nc\nd";
+        assert_eq!(TextFormat::detect(mixed).line_ending, LineEnding::CrLf);
+        assert_eq!(
+            TextFormat::detect(b"a\r\nb\nc\n").line_ending,
+            LineEnding::Lf
+        );
+
+        let format = TextFormat::detect(b"\xef\xbb\xbfa\r\n");
+        assert_eq!(format.encoding, Encoding::Utf8);
+        assert!(format.bom);
+        assert_eq!(format.decode(b"\xef\xbb\xbfa\r\n"), "a\r\n");
+        assert_eq!(
+            format.decode_strict(b"\xef\xbb\xbfa\r\n").as_deref(),
+            Some("a\r\n")
+        );
+        // Latin-1 的字节不能按 UTF-8 解码
+        assert_eq!(TextFormat::default().decode_strict(b"# caf\xe9\n"), None);
+    }
+
+    #[test]
+    fn test_utf16_round_trip() {
+        let text = "127.0.0.1 localhost\r\n# 注释\r\n";
+        for encoding in [Encoding::Utf16Le, Encoding::Utf16Be] {
+            let format = TextFormat {
+                encoding,
+                bom: true,
+                line_ending: LineEnding::CrLf,
+            };
+            let content = format.encode(text);
+            assert_eq!(TextFormat::detect(&content), format);
+            assert_eq!(format.decode(&content), text);
+        }
+    }
+
+    #[test]
+    fn test_apply_line_ending() {
+        assert_eq!(LineEnding::CrLf.apply("a\nb\r\nc"), "a\r\nb\r\nc");
+        assert_eq!(LineEnding::Lf.apply("a\r\nb\n"), "a\nb\n");
+    }
+}
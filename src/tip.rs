@@ -2,14 +2,14 @@ use ratatui::{
     buffer::Buffer,
     layout::Rect,
     style::{Color, Modifier, Style},
-    text::{Line, Span},
+    text::{Line, Span, Text},
     widgets::WidgetRef,
 };
 
 #[derive(Debug, Default)]
 pub struct Tip<'a> {
     which: usize,
-    lines: [Line<'a>; 3],
+    lines: [Line<'a>; 4],
 }
 impl<'a> Tip<'a> {
     pub fn new() -> Self {
@@ -19,10 +19,34 @@ impl<'a> Tip<'a> {
         let edit_list_message_line = Line::from(vec![
             Span::styled("Shift+N", strong_style),
             Span::raw(" 添加hosts "),
+            Span::styled("Shift+A", strong_style),
+            Span::raw(" 添加远程hosts "),
+            Span::styled("Shift+G", strong_style),
+            Span::raw(" 新建分组 "),
+            Span::styled("G", strong_style),
+            Span::raw(" 编辑分组成员 "),
+            Span::styled("X", strong_style),
+            Span::raw(" 互斥集合 "),
+            Span::styled("T", strong_style),
+            Span::raw(" 写入目标 "),
+            Span::styled("I", strong_style),
+            Span::raw(" 导入系统hosts中的行 "),
+            Span::styled("Shift+E", strong_style),
+            Span::raw(" 编辑系统hosts "),
+            Span::styled("O", strong_style),
+            Span::raw(" 导出hosts "),
+            Span::styled("U", strong_style),
+            Span::raw(" 刷新远程hosts "),
             Span::styled("Shift+D", strong_style),
             Span::raw(" 删除hosts "),
             Span::styled("Shift+M", strong_style),
             Span::raw(" 修改标题 "),
+            Span::styled("P", strong_style),
+            Span::raw(" 预览切换结果 "),
+            Span::styled("Shift+R", strong_style),
+            Span::raw(" 备份 "),
+            Span::styled("Shift+L", strong_style),
+            Span::raw(" 清除缓存的密码 "),
             Span::styled("→", strong_style),
             Span::raw("进入编辑"),
             Span::styled("Ctrl+C", strong_style),
@@ -34,12 +58,20 @@ impl<'a> Tip<'a> {
         ]);
         let edit_title_message_line: Line<'_> =
             Line::from(vec![Span::styled("Esc", strong_style), Span::raw(" 退出 ")]);
+        let edit_system_message_line = Line::from(vec![
+            Span::styled("Esc", strong_style),
+            Span::raw(" 预览修改并写入 "),
+            Span::styled("Ctrl+Z", strong_style),
+            Span::raw(" 撤销 "),
+            Span::raw("受管理区块已锁定"),
+        ]);
         return Tip {
             which: 0,
             lines: [
                 edit_list_message_line,
                 edit_hosts_message_line,
                 edit_title_message_line,
+                edit_system_message_line,
             ],
         };
     }
@@ -48,7 +80,46 @@ impl<'a> Tip<'a> {
         self.which = idx;
     }
 
+    /// 按宽度折行后需要的行数
+    pub fn height(&self, width: u16) -> u16 {
+        self.rows(width).len() as u16
+    }
+
+    /// 以“按键 + 说明”为单位折行，避免提示在窄终端中被截断
+    fn rows(&self, width: u16) -> Vec<Line<'a>> {
+        let mut rows = vec![Line::default()];
+        for hint in self.lines[self.which].spans.chunks(2) {
+            let hint_width: usize = hint.iter().map(Span::width).sum();
+            let row_width = rows.last().map_or(0, Line::width);
+            if row_width > 0 && row_width + hint_width > width as usize {
+                rows.push(Line::default());
+            }
+            if let Some(row) = rows.last_mut() {
+                row.spans.extend_from_slice(hint);
+            }
+        }
+        rows
+    }
+
     pub fn draw(&mut self, area: Rect, buf: &mut Buffer) {
-        self.lines[self.which].render_ref(area, buf);
+        Text::from(self.rows(area.width)).render_ref(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_hints() {
+        let tip = Tip::new();
+        let rows = tip.rows(80);
+        assert!(rows.len() > 1);
+        assert_eq!(tip.height(80) as usize, rows.len());
+        assert!(rows.iter().all(|row| row.width() <= 80));
+        let spans: usize = rows.iter().map(|row| row.spans.len()).sum();
+        assert_eq!(spans, tip.lines[0].spans.len());
+        assert!(rows[0].spans[0].content == "Shift+N");
+        assert_eq!(tip.height(1000), 1);
     }
 }
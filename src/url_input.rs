@@ -0,0 +1,51 @@
+use crate::single_line_textarea::{
+    create_new_single_line_textarea, SingleLineTextarea, SinglelineTextareaType,
+};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{buffer::Buffer, layout::Rect};
+
+#[derive(Debug, Default)]
+pub struct UrlInput<'a> {
+    textarea: SingleLineTextarea<'a>,
+}
+
+impl<'a> UrlInput<'a> {
+    pub fn new() -> Self {
+        UrlInput {
+            textarea: create_new_single_line_textarea(
+                "http(s)://",
+                "远程 hosts 地址",
+                SinglelineTextareaType::Text,
+            ),
+        }
+    }
+
+    pub fn handle_event(&mut self, event: KeyEvent) -> (bool, Option<String>) {
+        match event.code {
+            KeyCode::Esc => {
+                self.textarea.set_text("");
+                (true, None)
+            }
+            KeyCode::Enter => {
+                let text = self.textarea.get_text().trim().to_owned();
+                if text.starts_with("http://") || text.starts_with("https://") {
+                    self.textarea.set_text("");
+                    (true, Some(text))
+                } else {
+                    self.textarea
+                        .set_error("请输入 http:// 或 https:// 开头的地址");
+                    (false, None)
+                }
+            }
+            _ => {
+                self.textarea.set_error("");
+                self.textarea.input(event);
+                (false, None)
+            }
+        }
+    }
+
+    pub fn draw(&mut self, area: Rect, buf: &mut Buffer) {
+        self.textarea.draw(area, buf);
+    }
+}
@@ -0,0 +1,98 @@
+use std::net::IpAddr;
+
+use crate::hosts_parser::HostsDocument;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub line_number: usize,
+    pub message: String,
+}
+
+/// 校验每一行映射，被注释掉的映射不校验
+pub fn validate_hosts(content: &str) -> Vec<Diagnostic> {
+    let doc = HostsDocument::parse(content);
+    let mut diagnostics = vec![];
+    for (line, entry) in doc.active_entries() {
+        let mut report = |message: String| {
+            diagnostics.push(Diagnostic {
+                line_number: line.line_number,
+                message,
+            })
+        };
+        if !is_valid_ip(&entry.ip) {
+            report(format!("无效的 IP 地址: {}", entry.ip));
+        }
+        if entry.hostnames.is_empty() {
+            report(String::from("缺少主机名"));
+        }
+        for hostname in entry.hostnames.iter().filter(|h| !is_valid_hostname(h)) {
+            report(format!("无效的主机名: {}", hostname));
+        }
+    }
+    diagnostics
+}
+
+/// 支持 IPv4、IPv6 以及带 zone 的 IPv6 地址（如 fe80::1%lo0）
+pub fn is_valid_ip(ip: &str) -> bool {
+    let addr = match ip.split_once('%') {
+        Some((addr, zone)) if !zone.is_empty() => addr,
+        Some(_) => return false,
+        None => ip,
+    };
+    match addr.parse::<IpAddr>() {
+        Ok(IpAddr::V4(_)) => !ip.contains('%'),
+        Ok(IpAddr::V6(_)) => true,
+        Err(_) => false,
+    }
+}
+
+/// RFC 1123 主机名：总长不超过 253，每段 1~63 个字母、数字或 `-`，且不以 `-` 开头或结尾
+pub fn is_valid_hostname(hostname: &str) -> bool {
+    let name = hostname.strip_suffix('.').unwrap_or(hostname);
+    if name.is_empty() || name.len() > 253 {
+        return false;
+    }
+    name.split('.').all(|label| {
+        !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_hosts() {
+        let content = "127.0.0.1 localhost\n127.0.0.1dev.local\n999.1.1.1 foo\n::1 ip6-localhost\n# 1.1 disabled\n10.0.0.1 bad_name.dev";
+        let diagnostics = validate_hosts(content);
+        let lines = diagnostics
+            .iter()
+            .map(|d| d.line_number)
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec![2, 2, 3, 6]);
+        assert_eq!(diagnostics[1].message, "缺少主机名");
+    }
+
+    #[test]
+    fn test_is_valid_ip() {
+        assert!(is_valid_ip("192.168.0.1"));
+        assert!(is_valid_ip("::1"));
+        assert!(is_valid_ip("fe80::1%lo0"));
+        assert!(!is_valid_ip("999.1.1.1"));
+        assert!(!is_valid_ip("127.0.0.1%lo0"));
+        assert!(!is_valid_ip("fe80::1%"));
+    }
+
+    #[test]
+    fn test_is_valid_hostname() {
+        assert!(is_valid_hostname("localhost"));
+        assert!(is_valid_hostname("api-1.dev.local."));
+        assert!(!is_valid_hostname("-api.dev"));
+        assert!(!is_valid_hostname("api..dev"));
+        assert!(!is_valid_hostname(&"a".repeat(64)));
+    }
+}